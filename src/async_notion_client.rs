@@ -0,0 +1,483 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::try_join_all;
+use reqwest::{Method, RequestBuilder};
+use serde_json::Value;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+
+use crate::credentials::redact;
+use crate::diagnostics::ParseDiagnostics;
+use crate::error::{Error, Result};
+use crate::exporter::PageSource;
+use crate::notion_client::{
+    block_children_url, collect_results, data_source_url, database_url, is_not_found,
+    next_retry_wait, page_is_given_error, page_url, pagination_params, parse_response,
+    property_items_url, query_body, query_url, request_headers, uses_data_sources,
+    validate_page_size, NotionClient, DEFAULT_MAX_RETRIES, DEFAULT_NOTION_VERSION,
+    DEFAULT_PAGE_SIZE, DEFAULT_RETRY_BACKOFF, NOTION_API_BASE_URL,
+};
+use crate::notion_comments::{parse_comments, NotionComment};
+use crate::notion_database::{parse_data_sources, parse_database_schema, NotionDatabaseSchema};
+use crate::notion_id::normalize_notion_id;
+use crate::notion_pages::{
+    complete_property_value, find_truncated_properties, parse_notion_page_list_with_diagnostics,
+    NotionPage,
+};
+use crate::query::QueryOptions;
+use crate::rate_limiter::{RateLimiter, DEFAULT_REQUESTS_PER_SECOND};
+
+pub static DEFAULT_MAX_CONCURRENCY: usize = 3;
+
+/// Pages fetched from a table of a database
+#[derive(Debug)]
+pub struct DatabasePages {
+    pub database_id: String,
+    pub schema: NotionDatabaseSchema,
+    pub pages: Vec<NotionPage>,
+}
+
+/// Async variant of `NotionClient`.
+/// All requests share one connection pool, and independent requests are sent in parallel
+/// under a global concurrency limit and a requests-per-second budget.
+pub struct AsyncNotionClient {
+    pub api_key: String,
+    /// Value of Notion-Version header like "2022-02-22"
+    pub notion_version: String,
+    /// Base URL of Notion API. It can be replaced with a mock server in tests.
+    pub base_url: String,
+    /// Number of items requested at once, from 1 to `MAX_PAGE_SIZE`
+    pub page_size: u32,
+    /// Filter and sorts of queries of pages
+    pub query: QueryOptions,
+    /// Rate limiter which all requests go through. It can be shared with other clients.
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Number of times a request is sent again on 429 or 5xx responses
+    pub max_retries: u32,
+    /// Wait before the first retry when the response has no Retry-After header
+    pub retry_backoff: Duration,
+    http: reqwest::Client,
+    concurrency: Arc<Semaphore>,
+}
+
+/// The API key is redacted so that the client can be logged
+impl fmt::Debug for AsyncNotionClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncNotionClient")
+            .field("api_key", &redact(&self.api_key))
+            .field("notion_version", &self.notion_version)
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+impl AsyncNotionClient {
+    pub fn new(api_key: &str) -> AsyncNotionClient {
+        AsyncNotionClient::with_limits(
+            api_key,
+            DEFAULT_MAX_CONCURRENCY,
+            DEFAULT_REQUESTS_PER_SECOND,
+        )
+    }
+
+    /// Create a client which sends at most `max_concurrency` requests at the same time
+    /// and at most `requests_per_second` requests per second on average. 0 disables the latter.
+    pub fn with_limits(
+        api_key: &str,
+        max_concurrency: usize,
+        requests_per_second: f64,
+    ) -> AsyncNotionClient {
+        AsyncNotionClient {
+            api_key: api_key.into(),
+            notion_version: DEFAULT_NOTION_VERSION.into(),
+            base_url: NOTION_API_BASE_URL.into(),
+            page_size: DEFAULT_PAGE_SIZE,
+            query: QueryOptions::default(),
+            rate_limiter: Arc::new(RateLimiter::new(requests_per_second)),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            http: reqwest::Client::new(),
+            concurrency: Arc::new(Semaphore::new(max_concurrency.max(1))),
+        }
+    }
+
+    /// Create a client configured like the blocking client, which shares its rate limiter
+    /// and sends at most `max_concurrency` requests at the same time.
+    pub fn from_client(client: &NotionClient, max_concurrency: usize) -> AsyncNotionClient {
+        AsyncNotionClient {
+            api_key: client.api_key.clone(),
+            notion_version: client.notion_version.clone(),
+            base_url: client.base_url.clone(),
+            page_size: client.page_size,
+            query: client.query.clone(),
+            rate_limiter: client.rate_limiter.clone(),
+            max_retries: client.max_retries,
+            retry_backoff: client.retry_backoff,
+            http: reqwest::Client::new(),
+            concurrency: Arc::new(Semaphore::new(max_concurrency.max(1))),
+        }
+    }
+
+    /// Fetch pages of all the databases in parallel
+    pub async fn get_pages_of_databases(
+        &self,
+        database_ids: &[String],
+    ) -> Result<(Vec<DatabasePages>, ParseDiagnostics)> {
+        let results = try_join_all(database_ids.iter().map(|database_id| async move {
+            let schemas = self.get_database_schemas(database_id).await?;
+            try_join_all(schemas.into_iter().map(|schema| async move {
+                let mut diagnostics = ParseDiagnostics::new();
+                let pages = self
+                    .get_all_pages(database_id, &schema, &mut diagnostics)
+                    .await?;
+                let database_pages = DatabasePages {
+                    database_id: database_id.clone(),
+                    schema,
+                    pages,
+                };
+                Ok::<_, Error>((database_pages, diagnostics))
+            }))
+            .await
+        }))
+        .await?;
+
+        let mut all_diagnostics = ParseDiagnostics::new();
+        let mut all_database_pages = vec![];
+        for (database_pages, mut diagnostics) in results.into_iter().flatten() {
+            all_diagnostics.append(&mut diagnostics);
+            all_database_pages.push(database_pages);
+        }
+        Ok((all_database_pages, all_diagnostics))
+    }
+
+    /// Fetch schemas of all tables which the database consists of.
+    /// Schemas of data sources are fetched in parallel.
+    pub async fn get_database_schemas(
+        &self,
+        database_id: &str,
+    ) -> Result<Vec<NotionDatabaseSchema>> {
+        if !uses_data_sources(&self.notion_version) {
+            return Ok(vec![self.get_database(database_id).await?]);
+        }
+
+        let resp = self.get_database_object(database_id).await?;
+        let data_sources = parse_data_sources(&resp)?;
+        info!("Data sources: {:?}", data_sources);
+        try_join_all(
+            data_sources
+                .iter()
+                .map(|data_source| self.get_data_source(&data_source.id)),
+        )
+        .await
+    }
+
+    pub async fn get_database(&self, database_id: &str) -> Result<NotionDatabaseSchema> {
+        let resp = self.get_database_object(database_id).await?;
+        parse_database_schema(&resp)
+    }
+
+    /// Fetch the database object.
+    /// If the id turns out to be of a page, it's reported as an invalid argument instead of a not found error.
+    async fn get_database_object(&self, database_id: &str) -> Result<Value> {
+        let url = database_url(&self.base_url, database_id)?;
+        match self.send(self.request(Method::GET, &url)?).await {
+            Err(err) if is_not_found(&err) => {
+                let url = page_url(&self.base_url, database_id)?;
+                match self.send(self.request(Method::GET, &url)?).await {
+                    Ok(_) => Err(page_is_given_error(&normalize_notion_id(database_id)?)),
+                    Err(_) => Err(err),
+                }
+            }
+            result => result,
+        }
+    }
+
+    pub async fn get_data_source(&self, data_source_id: &str) -> Result<NotionDatabaseSchema> {
+        let url = data_source_url(&self.base_url, data_source_id)?;
+        let resp = self.send(self.request(Method::GET, &url)?).await?;
+        parse_database_schema(&resp)
+    }
+
+    /// Fetch all pages of the database.
+    /// Pages are paginated with cursors, so requests for a single database are sent sequentially.
+    pub async fn get_all_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        let mut next_cursor: Option<String> = None;
+        let mut all_pages: Vec<NotionPage> = vec![];
+        loop {
+            let (mut pages, _next_cursor) = self
+                .get_pages(database_id, schema, next_cursor.as_deref(), diagnostics)
+                .await?;
+            all_pages.append(&mut pages);
+            next_cursor = _next_cursor;
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(all_pages)
+    }
+
+    /// Fetch a batch of pages of the database starting at the cursor,
+    /// with the cursor of the next batch if there are more pages.
+    pub async fn get_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        validate_page_size(self.page_size)?;
+        let database_id = normalize_notion_id(database_id)?;
+        let url = query_url(&self.base_url, &database_id, schema);
+        let query = self.query.to_json(schema)?;
+        let query_str = query_body(&query, self.page_size, start_cursor).to_string();
+        let mut resp = self
+            .send(
+                self.request(Method::POST, &url)?
+                    .header("Content-Type", "application/json")
+                    .body(query_str),
+            )
+            .await?;
+        self.complete_truncated_properties(&mut resp).await?;
+        parse_notion_page_list_with_diagnostics(schema, &resp, diagnostics)
+    }
+
+    /// Fetch all child blocks of the block or the page
+    pub async fn get_block_children(&self, block_id: &str) -> Result<Vec<Value>> {
+        let url = block_children_url(&self.base_url, block_id)?;
+        self.get_paginated(&url).await
+    }
+
+    /// Fetch child blocks of many blocks or pages in parallel.
+    /// The result is in the same order as `block_ids`.
+    pub async fn get_block_children_of_many(
+        &self,
+        block_ids: &[String],
+    ) -> Result<Vec<Vec<Value>>> {
+        try_join_all(
+            block_ids
+                .iter()
+                .map(|block_id| self.get_block_children(block_id)),
+        )
+        .await
+    }
+
+    /// Fetch all items of a page property.
+    /// See https://developers.notion.com/reference/retrieve-a-page-property
+    pub async fn get_property_items(&self, page_id: &str, property_id: &str) -> Result<Vec<Value>> {
+        let url = property_items_url(&self.base_url, page_id, property_id)?;
+        self.get_paginated(&url).await
+    }
+
+    /// Fetch items of many page properties, given as pairs of page id and property id, in parallel.
+    /// The result is in the same order as `properties`.
+    pub async fn get_property_items_of_many(
+        &self,
+        properties: &[(String, String)],
+    ) -> Result<Vec<Vec<Value>>> {
+        try_join_all(
+            properties
+                .iter()
+                .map(|(page_id, property_id)| self.get_property_items(page_id, property_id)),
+        )
+        .await
+    }
+
+    /// Fetch comments on the page, and on blocks in it if `include_blocks` is true.
+    /// Comments on child blocks of a block are fetched in parallel.
+    pub async fn get_comments(
+        &self,
+        page_id: &str,
+        include_blocks: bool,
+    ) -> Result<Vec<NotionComment>> {
+        let page_id = normalize_notion_id(page_id)?;
+        let mut comments = self.get_block_comments(&page_id, &page_id).await?;
+        if !include_blocks {
+            return Ok(comments);
+        }
+
+        let mut parents = vec![page_id.clone()];
+        while let Some(parent_id) = parents.pop() {
+            let mut block_ids = vec![];
+            for block in self.get_block_children(&parent_id).await? {
+                let block_id = match block.get("id").and_then(|id| id.as_str()) {
+                    Some(block_id) => block_id.to_string(),
+                    None => continue,
+                };
+                let block_type = block.get("type").and_then(|t| t.as_str());
+                if matches!(block_type, Some("child_page") | Some("child_database")) {
+                    continue;
+                }
+                if block.get("has_children").and_then(|h| h.as_bool()) == Some(true) {
+                    parents.push(block_id.clone());
+                }
+                block_ids.push(block_id);
+            }
+            let block_comments = try_join_all(
+                block_ids
+                    .iter()
+                    .map(|block_id| self.get_block_comments(&page_id, block_id)),
+            )
+            .await?;
+            comments.extend(block_comments.into_iter().flatten());
+        }
+        Ok(comments)
+    }
+
+    /// Fetch comments on the block, which is the page itself or a block in it
+    async fn get_block_comments(
+        &self,
+        page_id: &str,
+        block_id: &str,
+    ) -> Result<Vec<NotionComment>> {
+        let url = format!(
+            "{0}/comments?block_id={1}",
+            self.base_url,
+            normalize_notion_id(block_id)?
+        );
+        let results = self.get_paginated(&url).await?;
+        parse_comments(&results, page_id)
+    }
+
+    /// Replace property values truncated by the query endpoint with all of their items.
+    /// Items of the truncated values are fetched in parallel.
+    async fn complete_truncated_properties(&self, query_resp: &mut Value) -> Result<()> {
+        let truncated = find_truncated_properties(query_resp);
+        let properties = truncated
+            .iter()
+            .map(|t| (t.page_id.clone(), t.property_id.clone()))
+            .collect::<Vec<_>>();
+        let all_items = self.get_property_items_of_many(&properties).await?;
+        for (truncated, items) in truncated.iter().zip(all_items) {
+            complete_property_value(query_resp, truncated, &items);
+        }
+        Ok(())
+    }
+
+    /// Follow `next_cursor` of a paginated GET endpoint and collect all results.
+    /// A response which isn't a list, e.g. a single property item, is returned as the only item.
+    async fn get_paginated(&self, url: &str) -> Result<Vec<Value>> {
+        validate_page_size(self.page_size)?;
+        let mut next_cursor: Option<String> = None;
+        let mut all_results: Vec<Value> = vec![];
+        loop {
+            let request = self
+                .request(Method::GET, url)?
+                .query(&pagination_params(self.page_size, next_cursor.as_deref()));
+            next_cursor = collect_results(self.send(request).await?, &mut all_results)?;
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(all_results)
+    }
+
+    /// Build a request with headers common to all Notion API calls
+    fn request(&self, method: Method, url: &str) -> Result<RequestBuilder> {
+        let headers = request_headers(&self.api_key, &self.notion_version)?;
+        Ok(self.http.request(method, url).headers(headers))
+    }
+
+    /// Send the request within the concurrency limit and the requests-per-second budget.
+    /// Requests which are rate limited or fail with a server error are sent again up to `max_retries` times,
+    /// without holding the concurrency limit while waiting.
+    async fn send(&self, mut request: RequestBuilder) -> Result<Value> {
+        let mut retries = 0;
+        loop {
+            // Requests only have JSON bodies, so they can always be cloned
+            let retry = request.try_clone();
+            let permit = self
+                .concurrency
+                .acquire()
+                .await
+                .expect("Semaphore is never closed");
+            self.rate_limiter.acquire().await;
+            let response = request.send().await?;
+            let status = response.status().as_u16();
+            let wait = next_retry_wait(
+                status,
+                response.headers(),
+                retries,
+                self.max_retries,
+                self.retry_backoff,
+            );
+            match (retry, wait) {
+                (Some(retry), Some(wait)) => {
+                    drop(permit);
+                    self.rate_limiter.record_retry();
+                    tokio::time::sleep(wait).await;
+                    request = retry;
+                    retries += 1;
+                }
+                _ => {
+                    let body = response.text().await?;
+                    return parse_response(status, &body);
+                }
+            }
+        }
+    }
+}
+
+/// Page source of the exporter which fetches with `AsyncNotionClient` on its own runtime,
+/// so that truncated property values, data source schemas and block comments are fetched in parallel.
+/// It must not be used from inside another tokio runtime.
+pub struct AsyncPageSource {
+    client: AsyncNotionClient,
+    runtime: Runtime,
+}
+
+impl AsyncPageSource {
+    /// Panics if the tokio runtime can't be started, like `reqwest::blocking` does.
+    pub fn new(client: AsyncNotionClient) -> AsyncPageSource {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start tokio runtime");
+        AsyncPageSource { client, runtime }
+    }
+}
+
+impl PageSource for AsyncPageSource {
+    fn get_database_schemas(&self, database_id: &str) -> Result<Vec<NotionDatabaseSchema>> {
+        self.runtime
+            .block_on(self.client.get_database_schemas(database_id))
+    }
+
+    fn get_all_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        self.runtime
+            .block_on(self.client.get_all_pages(database_id, schema, diagnostics))
+    }
+
+    fn get_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        self.runtime.block_on(
+            self.client
+                .get_pages(database_id, schema, start_cursor, diagnostics),
+        )
+    }
+
+    fn query(&self, schema: &NotionDatabaseSchema) -> Result<Value> {
+        self.client.query.to_json(schema)
+    }
+
+    fn get_comments(&self, page_id: &str, include_blocks: bool) -> Result<Vec<NotionComment>> {
+        self.runtime
+            .block_on(self.client.get_comments(page_id, include_blocks))
+    }
+}
@@ -0,0 +1,226 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Environment variable which the API key is read from
+pub static API_KEY_ENV: &str = "NOTION_API_KEY";
+
+/// Where the API key was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeySource {
+    Flag,
+    File(PathBuf),
+    Env,
+    Config(PathBuf),
+}
+impl fmt::Display for ApiKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiKeySource::Flag => write!(f, "--api-key"),
+            ApiKeySource::File(path) => write!(f, "file {}", path.display()),
+            ApiKeySource::Env => write!(f, "{}", API_KEY_ENV),
+            ApiKeySource::Config(path) => write!(f, "config file {}", path.display()),
+        }
+    }
+}
+
+/// Credentials section of the config file.
+///
+/// ```toml
+/// [credentials]
+/// api_key = "secret_xxx"
+/// # or a path to a file which contains the key
+/// api_key_file = "~/.config/notion-into-sqlite/api_key"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigCredentials {
+    pub api_key: Option<String>,
+    pub api_key_file: Option<PathBuf>,
+}
+
+/// Default path of the config file, `$XDG_CONFIG_HOME/notion-into-sqlite/config.toml`
+/// or `~/.config/notion-into-sqlite/config.toml`
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("notion-into-sqlite").join("config.toml"))
+}
+
+/// Read the credentials section of the config file
+pub fn read_config_credentials(path: &Path) -> Result<ConfigCredentials> {
+    let invalid = |message: String| {
+        Error::InvalidArgument(format!(
+            "Invalid config file {}: {}",
+            path.display(),
+            message
+        ))
+    };
+    let content = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+    let config = content
+        .parse::<toml::Value>()
+        .map_err(|err| invalid(err.to_string()))?;
+
+    let credentials = match config.get("credentials") {
+        Some(credentials) => credentials,
+        None => return Ok(ConfigCredentials::default()),
+    };
+    let string_field = |key: &str| match credentials.get(key) {
+        None => Ok(None),
+        Some(toml::Value::String(value)) => Ok(Some(value.to_string())),
+        Some(_) => Err(invalid(format!("credentials.{} must be a string", key))),
+    };
+    Ok(ConfigCredentials {
+        api_key: string_field("api_key")?,
+        api_key_file: string_field("api_key_file")?.map(|file| expand_home(&file)),
+    })
+}
+
+/// Resolve the API key in order of the flag, the key file, the environment variable and the config file.
+/// The config file is only read if the key isn't found elsewhere.
+pub fn resolve_api_key(
+    flag: Option<&str>,
+    api_key_file: Option<&Path>,
+    config_path: Option<&Path>,
+) -> Result<(String, ApiKeySource)> {
+    if let Some(api_key) = flag {
+        return Ok((validate_api_key(api_key, "--api-key")?, ApiKeySource::Flag));
+    }
+    if let Some(path) = api_key_file {
+        let api_key = read_api_key_file(path)?;
+        return Ok((api_key, ApiKeySource::File(path.to_path_buf())));
+    }
+    if let Ok(api_key) = env::var(API_KEY_ENV) {
+        if !api_key.trim().is_empty() {
+            return Ok((validate_api_key(&api_key, API_KEY_ENV)?, ApiKeySource::Env));
+        }
+    }
+    if let Some(config_path) = config_path.filter(|path| path.exists()) {
+        let credentials = read_config_credentials(config_path)?;
+        if let Some(api_key) = credentials.api_key {
+            let api_key = validate_api_key(&api_key, "credentials.api_key")?;
+            return Ok((api_key, ApiKeySource::Config(config_path.to_path_buf())));
+        }
+        if let Some(path) = credentials.api_key_file {
+            let api_key = read_api_key_file(&path)?;
+            return Ok((api_key, ApiKeySource::File(path)));
+        }
+    }
+    Err(Error::InvalidArgument(format!(
+        "Notion API key is not specified. Set {}, or pass --api-key-file or --api-key",
+        API_KEY_ENV
+    )))
+}
+
+/// Mask the key so that it can be logged, e.g. "secr****"
+pub fn redact(api_key: &str) -> String {
+    let visible = api_key.chars().take(4).collect::<String>();
+    format!("{}****", visible)
+}
+
+fn read_api_key_file(path: &Path) -> Result<String> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        Error::InvalidArgument(format!(
+            "Failed to read API key file {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    validate_api_key(&content, &path.display().to_string())
+}
+
+fn validate_api_key(api_key: &str, source: &str) -> Result<String> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err(Error::InvalidArgument(format!(
+            "API key in {} is empty",
+            source
+        )));
+    }
+    Ok(api_key.to_string())
+}
+
+/// Expand "~/" at the beginning of the path into the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("secret_abcdefg"), "secr****");
+        assert_eq!(redact("ab"), "ab****");
+    }
+
+    #[test]
+    fn test_resolve_api_key_from_flag_and_file() {
+        let dir = env::temp_dir().join(format!("notion-into-sqlite-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("api_key");
+        fs::write(&key_file, "secret_from_file\n").unwrap();
+
+        let (api_key, source) =
+            resolve_api_key(Some("secret_flag"), Some(&key_file), None).unwrap();
+        assert_eq!(api_key, "secret_flag");
+        assert_eq!(source, ApiKeySource::Flag);
+
+        let (api_key, source) = resolve_api_key(None, Some(&key_file), None).unwrap();
+        assert_eq!(api_key, "secret_from_file");
+        assert_eq!(source, ApiKeySource::File(key_file.clone()));
+
+        let config_file = dir.join("config.toml");
+        fs::write(
+            &config_file,
+            format!(
+                "[credentials]\napi_key_file = {:?}\n",
+                key_file.display().to_string()
+            ),
+        )
+        .unwrap();
+        let credentials = read_config_credentials(&config_file).unwrap();
+        assert_eq!(credentials.api_key, None);
+        assert_eq!(credentials.api_key_file, Some(key_file.clone()));
+
+        fs::write(&config_file, "[credentials]\napi_key = 1\n").unwrap();
+        assert!(read_config_credentials(&config_file).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // The only test which touches the environment variable, so that parallel tests don't race on it
+    #[test]
+    fn test_resolve_api_key_from_env_before_config() {
+        let dir = env::temp_dir().join(format!("notion-into-sqlite-env-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("config.toml");
+        fs::write(&config_file, "[credentials]\napi_key = \"secret_config\"\n").unwrap();
+        let original = env::var_os(API_KEY_ENV);
+
+        env::set_var(API_KEY_ENV, "secret_env");
+        let env_result = resolve_api_key(None, None, Some(&config_file));
+        env::remove_var(API_KEY_ENV);
+        let config_result = resolve_api_key(None, None, Some(&config_file));
+        if let Some(original) = original {
+            env::set_var(API_KEY_ENV, original);
+        }
+
+        let (api_key, source) = env_result.unwrap();
+        assert_eq!(api_key, "secret_env");
+        assert_eq!(source, ApiKeySource::Env);
+        let (api_key, source) = config_result.unwrap();
+        assert_eq!(api_key, "secret_config");
+        assert_eq!(source, ApiKeySource::Config(config_file.clone()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
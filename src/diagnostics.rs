@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// A page or property which was skipped while parsing Notion API responses
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDiagnostic {
+    /// Page id if it could be read
+    pub page_id: Option<String>,
+    /// Name of the skipped property. None if the whole page was skipped.
+    pub property_name: Option<String>,
+    /// JSON path to the offending value in the response like `results[0].properties["Age"].number`
+    pub json_path: String,
+    pub reason: String,
+}
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let page_id = self.page_id.as_deref().unwrap_or("<unknown>");
+        match &self.property_name {
+            Some(property_name) => write!(
+                f,
+                r#"Skipped property "{}" of page {} at {}: {}"#,
+                property_name, page_id, self.json_path, self.reason
+            ),
+            None => write!(
+                f,
+                "Skipped page {} at {}: {}",
+                page_id, self.json_path, self.reason
+            ),
+        }
+    }
+}
+
+/// Collects pages and properties which were skipped during parsing,
+/// so that they can be reported instead of silently disappearing from the output.
+#[derive(Debug, Default)]
+pub struct ParseDiagnostics {
+    pub items: Vec<ParseDiagnostic>,
+}
+impl ParseDiagnostics {
+    pub fn new() -> ParseDiagnostics {
+        ParseDiagnostics::default()
+    }
+
+    pub fn push(&mut self, diagnostic: ParseDiagnostic) {
+        warn!("{}", diagnostic);
+        self.items.push(diagnostic);
+    }
+
+    /// Move all items of `other` into this
+    pub fn append(&mut self, other: &mut ParseDiagnostics) {
+        self.items.append(&mut other.items);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn skipped_pages(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.property_name.is_none())
+            .count()
+    }
+
+    /// Ids of the pages which were skipped as a whole, as far as they could be read
+    pub fn skipped_page_ids(&self) -> HashSet<&str> {
+        self.items
+            .iter()
+            .filter(|item| item.property_name.is_none())
+            .filter_map(|item| item.page_id.as_deref())
+            .collect()
+    }
+
+    pub fn skipped_properties(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.property_name.is_some())
+            .count()
+    }
+
+    /// One line summary like "Skipped 1 page(s) and 2 property value(s) while parsing"
+    pub fn summary(&self) -> String {
+        format!(
+            "Skipped {} page(s) and {} property value(s) while parsing",
+            self.skipped_pages(),
+            self.skipped_properties()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut diagnostics = ParseDiagnostics::new();
+        assert!(diagnostics.is_empty());
+
+        diagnostics.push(ParseDiagnostic {
+            page_id: Some("xxxx".into()),
+            property_name: None,
+            json_path: "results[0].url".into(),
+            reason: "field is missing".into(),
+        });
+        diagnostics.push(ParseDiagnostic {
+            page_id: Some("yyyy".into()),
+            property_name: Some("Age".into()),
+            json_path: r#"results[1].properties["Age"].number"#.into(),
+            reason: "value is null".into(),
+        });
+        assert_eq!(diagnostics.skipped_pages(), 1);
+        assert_eq!(diagnostics.skipped_properties(), 1);
+        assert_eq!(
+            diagnostics.summary(),
+            "Skipped 1 page(s) and 1 property value(s) while parsing"
+        );
+        assert_eq!(
+            diagnostics.items[1].to_string(),
+            r#"Skipped property "Age" of page yyyy at results[1].properties["Age"].number: value is null"#
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::notion_database::NotionDatabaseSchema;
+use crate::notion_pages::NotionPage;
+use crate::sqlite::ColumnNames;
+
+/// Changes which writing pages would make to a table, reported in dry-run mode
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableDiff {
+    pub table_name: String,
+    /// Whether the table doesn't exist yet
+    pub new_table: bool,
+    /// Ids of pages which aren't in the table yet
+    pub inserted: Vec<String>,
+    /// Ids of pages whose last edited time differs from the one in the table
+    pub updated: Vec<String>,
+    /// Ids of pages in the table which are no longer in the database
+    pub deleted: Vec<String>,
+    /// Number of pages which are the same as the ones in the table
+    pub unchanged: usize,
+    /// Properties which don't have columns in the table yet
+    pub added_columns: Vec<String>,
+}
+impl TableDiff {
+    /// Add changes of another batch of pages of the same table.
+    /// Deleted pages aren't merged, since each batch reports all pages which aren't in it.
+    pub fn merge(&mut self, batch: TableDiff) {
+        self.inserted.extend(batch.inserted);
+        self.updated.extend(batch.updated);
+        self.unchanged += batch.unchanged;
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.new_table
+            || !self.inserted.is_empty()
+            || !self.updated.is_empty()
+            || !self.deleted.is_empty()
+            || !self.added_columns.is_empty()
+    }
+}
+
+/// Rows which a table already holds
+#[derive(Debug, Clone, Default)]
+pub struct ExistingTable {
+    /// Whether the table exists. Other fields are empty if it doesn't.
+    pub exists: bool,
+    /// Names of the columns of the table
+    pub columns: Vec<String>,
+    /// Last edited time of each page by page id. It's None if the page has no metadata.
+    pub last_edited_times: HashMap<String, Option<String>>,
+}
+
+/// Compare incoming pages with the rows of the table, keyed by page id and last edited time
+pub fn diff_pages(
+    schema: &NotionDatabaseSchema,
+    table_name: &str,
+    existing: &ExistingTable,
+    pages: &[NotionPage],
+) -> TableDiff {
+    let mut diff = TableDiff {
+        table_name: table_name.to_string(),
+        ..Default::default()
+    };
+    if !existing.exists {
+        diff.new_table = true;
+        diff.inserted = pages.iter().map(|page| page.id.clone()).collect();
+        return diff;
+    }
+
+    let column_names = ColumnNames::new(schema);
+    diff.added_columns = column_names
+        .header()
+        .into_iter()
+        .filter(|column| !existing.columns.iter().any(|existing| existing == column))
+        .map(|column| column.to_string())
+        .collect();
+
+    let mut incoming_ids = HashSet::new();
+    for page in pages {
+        incoming_ids.insert(page.id.as_str());
+        match existing.last_edited_times.get(&page.id) {
+            None => diff.inserted.push(page.id.clone()),
+            Some(Some(last_edited_time)) if *last_edited_time == page.last_edited_time => {
+                diff.unchanged += 1
+            }
+            Some(_) => diff.updated.push(page.id.clone()),
+        }
+    }
+    diff.deleted = existing
+        .last_edited_times
+        .keys()
+        .filter(|page_id| !incoming_ids.contains(page_id.as_str()))
+        .cloned()
+        .collect();
+    diff.deleted.sort();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notion_database::parse_database_schema;
+    use serde_json::json;
+
+    fn page(id: &str, last_edited_time: &str) -> NotionPage {
+        NotionPage {
+            id: id.to_string(),
+            properties: HashMap::new(),
+            url: "".to_string(),
+            created_time: "2022-03-12T00:15:00.000Z".to_string(),
+            created_by: json!({}),
+            last_edited_time: last_edited_time.to_string(),
+            last_edited_by: json!({}),
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn test_diff_pages() {
+        let schema = parse_database_schema(&json!({
+            "object": "database",
+            "id": "database1",
+            "title": [{"plain_text": "Tasks"}],
+            "properties": {
+                "Name": {"id": "title", "name": "Name", "type": "title", "title": {}},
+                "Due": {"id": "a", "name": "Due", "type": "date", "date": {}},
+            },
+        }))
+        .unwrap();
+        let existing = ExistingTable {
+            exists: true,
+            columns: vec!["page_id".to_string(), "Name".to_string()],
+            last_edited_times: HashMap::from([
+                (
+                    "a".to_string(),
+                    Some("2022-03-12T00:15:00.000Z".to_string()),
+                ),
+                (
+                    "b".to_string(),
+                    Some("2022-03-12T00:15:00.000Z".to_string()),
+                ),
+                ("c".to_string(), None),
+                (
+                    "d".to_string(),
+                    Some("2022-03-12T00:15:00.000Z".to_string()),
+                ),
+            ]),
+        };
+        let pages = vec![
+            page("a", "2022-03-12T00:15:00.000Z"),
+            page("b", "2022-03-13T00:00:00.000Z"),
+            page("c", "2022-03-12T00:15:00.000Z"),
+            page("e", "2022-03-12T00:15:00.000Z"),
+        ];
+
+        let diff = diff_pages(&schema, "pages", &existing, &pages);
+        assert!(!diff.new_table);
+        assert_eq!(diff.inserted, vec!["e"]);
+        assert_eq!(diff.updated, vec!["b", "c"]);
+        assert_eq!(diff.deleted, vec!["d"]);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.added_columns, vec!["Due"]);
+        assert!(diff.has_changes());
+
+        let mut merged = diff_pages(&schema, "pages", &existing, &pages[..2]);
+        merged.merge(diff_pages(&schema, "pages", &existing, &pages[2..]));
+        assert_eq!(merged.inserted, diff.inserted);
+        assert_eq!(merged.updated, diff.updated);
+        assert_eq!(merged.unchanged, diff.unchanged);
+
+        let diff = diff_pages(&schema, "pages", &ExistingTable::default(), &pages);
+        assert!(diff.new_table);
+        assert_eq!(diff.inserted.len(), 4);
+        assert!(diff.added_columns.is_empty());
+    }
+}
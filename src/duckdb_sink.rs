@@ -0,0 +1,386 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use duckdb::types::Value as DuckDbValue;
+use duckdb::{params_from_iter, Connection};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::exporter::{DatabaseRecord, Sink};
+use crate::notion_comments::NotionComment;
+use crate::notion_database::{NotionDatabaseSchema, NotionPropertyType};
+use crate::notion_pages::{NotionPage, NotionPropertyValue};
+use crate::sqlite::{
+    comment_row, database_row, page_metadata_row, ColumnNames, COMMENTS_COLUMNS, COMMENTS_TABLE,
+    DATABASES_COLUMNS, DATABASES_TABLE, PAGE_ID_COLUMN, PAGE_METADATA_COLUMNS, PAGE_METADATA_TABLE,
+};
+
+/// Type of a DuckDB column for a Notion property
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Varchar,
+    Double,
+    Boolean,
+    Timestamp,
+    /// List of the field of each item, such as names of multi-select options or ids of related pages
+    VarcharList(&'static str),
+    /// Date property with its start, end and time zone
+    Date,
+}
+impl ColumnType {
+    fn from_property_type(property_type: &NotionPropertyType) -> ColumnType {
+        match property_type {
+            NotionPropertyType::Number => ColumnType::Double,
+            NotionPropertyType::Checkbox => ColumnType::Boolean,
+            NotionPropertyType::CreatedTime | NotionPropertyType::LastEditedTime => {
+                ColumnType::Timestamp
+            }
+            NotionPropertyType::Date => ColumnType::Date,
+            NotionPropertyType::MultiSelect => ColumnType::VarcharList("name"),
+            NotionPropertyType::Relation | NotionPropertyType::People => {
+                ColumnType::VarcharList("id")
+            }
+            _ => ColumnType::Varchar,
+        }
+    }
+
+    fn sql_type(&self) -> &'static str {
+        match self {
+            ColumnType::Varchar => "VARCHAR",
+            ColumnType::Double => "DOUBLE",
+            ColumnType::Boolean => "BOOLEAN",
+            ColumnType::Timestamp => "TIMESTAMP",
+            ColumnType::VarcharList(_) => "VARCHAR[]",
+            ColumnType::Date => r#"STRUCT("start" TIMESTAMP, "end" TIMESTAMP, time_zone VARCHAR)"#,
+        }
+    }
+
+    /// SQL expression of the value in a VALUES clause, whose values are bound to placeholders pushed into `params`.
+    /// Lists and structs can't be bound as they are, so each of their items is bound to its own placeholder.
+    fn value_sql(&self, value: &NotionPropertyValue, params: &mut Vec<DuckDbValue>) -> String {
+        match self {
+            ColumnType::VarcharList(key) => list_sql(value, key, params),
+            ColumnType::Date => date_sql(value, params),
+            ColumnType::Timestamp => {
+                params.push(timestamp_text(value));
+                "TRY_CAST(? AS TIMESTAMP)".to_string()
+            }
+            ColumnType::Double => {
+                params.push(match value {
+                    NotionPropertyValue::Number(number) => DuckDbValue::Double(*number),
+                    _ => DuckDbValue::Null,
+                });
+                "?".to_string()
+            }
+            ColumnType::Boolean => {
+                params.push(match value {
+                    NotionPropertyValue::Boolean(boolean) => DuckDbValue::Boolean(*boolean),
+                    _ => DuckDbValue::Null,
+                });
+                "?".to_string()
+            }
+            ColumnType::Varchar => {
+                params.push(match value {
+                    NotionPropertyValue::Null => DuckDbValue::Null,
+                    value => DuckDbValue::Text(value.to_text()),
+                });
+                "?".to_string()
+            }
+        }
+    }
+}
+
+/// Sink which writes pages into a DuckDB file.
+/// Tables and column names are the same as those of `SqliteSink`, but properties are stored as native types
+/// such as `TIMESTAMP`, `BOOLEAN`, `VARCHAR[]` for multi-selects and `STRUCT` for dates instead of JSON text.
+pub struct DuckDbSink {
+    pub path: String,
+}
+impl DuckDbSink {
+    pub fn new(path: &str) -> DuckDbSink {
+        DuckDbSink {
+            path: path.to_string(),
+        }
+    }
+
+    /// Check if the database file doesn't exist yet
+    pub fn validate_database_path(path: &str) -> Result<()> {
+        if Path::new(path).exists() {
+            return Err(Error::Output {
+                path: path.to_string(),
+                message: "already exists".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        Connection::open(&self.path).map_err(|err| self.output_error(err))
+    }
+
+    /// Create the table if it doesn't exist, add columns which it lacks, and insert or replace the rows.
+    /// Column names must be escaped already, and the first column is the primary key.
+    fn write_table(
+        &self,
+        table_name: &str,
+        columns: &[(&str, ColumnType)],
+        rows: impl IntoIterator<Item = Vec<NotionPropertyValue>>,
+    ) -> Result<()> {
+        let mut conn = self.connect()?;
+        let definition = columns
+            .iter()
+            .enumerate()
+            .map(|(i, (column, column_type))| {
+                let constraint = if i == 0 { " PRIMARY KEY" } else { "" };
+                format!(r#""{}" {}{}"#, column, column_type.sql_type(), constraint)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!("CREATE TABLE IF NOT EXISTS {} ({})", table_name, definition);
+        debug!("{}", sql);
+        conn.execute(&sql, [])
+            .map_err(|err| self.output_error(err))?;
+        add_missing_columns(&conn, table_name, columns).map_err(|err| self.output_error(err))?;
+
+        let column_list = columns
+            .iter()
+            .map(|(column, _)| format!(r#""{}""#, column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let transaction = conn.transaction().map_err(|err| self.output_error(err))?;
+        // The statement only differs by the number of list items, so it's mostly reused from the cache
+        for row in rows {
+            let mut params = vec![];
+            let values = columns
+                .iter()
+                .zip(&row)
+                .map(|((_, column_type), value)| column_type.value_sql(value, &mut params))
+                .collect::<Vec<_>>();
+            let sql = format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                table_name,
+                column_list,
+                values.join(", ")
+            );
+            transaction
+                .prepare_cached(&sql)
+                .and_then(|mut statement| statement.execute(params_from_iter(params)))
+                .map_err(|err| self.output_error(err))?;
+        }
+        transaction.commit().map_err(|err| self.output_error(err))?;
+        Ok(())
+    }
+
+    /// Columns of the page properties table in the order of `ColumnNames::header()`
+    fn page_columns(schema: &NotionDatabaseSchema) -> Vec<(String, ColumnType)> {
+        let column_names = ColumnNames::new(schema);
+        let mut columns = vec![(PAGE_ID_COLUMN.to_string(), ColumnType::Varchar)];
+        for name in column_names.property_names() {
+            let property_type = &schema.properties[name].property_type;
+            columns.push((
+                column_names.resolve(name).to_string(),
+                ColumnType::from_property_type(property_type),
+            ));
+        }
+        columns
+    }
+
+    fn output_error(&self, err: duckdb::Error) -> Error {
+        Error::Output {
+            path: self.path.clone(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Sink for DuckDbSink {
+    fn create_table(&mut self, schema: &NotionDatabaseSchema, table_name: &str) -> Result<()> {
+        let columns = DuckDbSink::page_columns(schema);
+        let columns = columns
+            .iter()
+            .map(|(name, column_type)| (name.as_str(), *column_type))
+            .collect::<Vec<_>>();
+        self.write_table(table_name, &columns, vec![])
+    }
+
+    fn write_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()> {
+        let column_names = ColumnNames::new(schema);
+        let columns = DuckDbSink::page_columns(schema);
+        let columns = columns
+            .iter()
+            .map(|(name, column_type)| (name.as_str(), *column_type))
+            .collect::<Vec<_>>();
+        let rows = pages.iter().map(|page| {
+            column_names
+                .row(page)
+                .into_iter()
+                .map(|value| value.into_owned())
+                .collect::<Vec<_>>()
+        });
+        self.write_table(table_name, &columns, rows)?;
+
+        let columns = PAGE_METADATA_COLUMNS.into_iter().zip([
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+            ColumnType::Timestamp,
+            ColumnType::Varchar,
+            ColumnType::Timestamp,
+            ColumnType::Varchar,
+            ColumnType::Boolean,
+        ]);
+        let rows = pages.iter().map(page_metadata_row);
+        self.write_table(PAGE_METADATA_TABLE, &columns.collect::<Vec<_>>(), rows)
+    }
+
+    fn write_databases(&mut self, databases: &[DatabaseRecord]) -> Result<()> {
+        let columns = DATABASES_COLUMNS.map(|column| (column, ColumnType::Varchar));
+        let rows = databases.iter().map(database_row);
+        self.write_table(DATABASES_TABLE, &columns, rows)
+    }
+
+    fn write_comments(&mut self, comments: &[NotionComment]) -> Result<()> {
+        let columns = COMMENTS_COLUMNS.into_iter().zip([
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+            ColumnType::Timestamp,
+            ColumnType::Varchar,
+            ColumnType::Varchar,
+        ]);
+        let rows = comments.iter().map(comment_row);
+        self.write_table(COMMENTS_TABLE, &columns.collect::<Vec<_>>(), rows)
+    }
+}
+
+/// Text of a timestamp like "2022-03-12T00:15:00.000Z", which DuckDB converts into UTC
+/// Add columns for properties which have been added since the table was created
+fn add_missing_columns(
+    conn: &Connection,
+    table_name: &str,
+    columns: &[(&str, ColumnType)],
+) -> duckdb::Result<()> {
+    let mut statement = conn.prepare(
+        "SELECT column_name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = ?",
+    )?;
+    let existing_columns = statement
+        .query_map([table_name], |row| row.get::<_, String>(0))?
+        .map(|name| name.map(|name| name.replace('"', r#""""#)))
+        .collect::<duckdb::Result<HashSet<_>>>()?;
+    for (column, column_type) in columns {
+        if existing_columns.contains(*column) {
+            continue;
+        }
+        let sql = format!(
+            r#"ALTER TABLE {} ADD COLUMN "{}" {}"#,
+            table_name,
+            column,
+            column_type.sql_type()
+        );
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+    }
+    Ok(())
+}
+
+fn timestamp_text(value: &NotionPropertyValue) -> DuckDbValue {
+    match value {
+        NotionPropertyValue::Text(text) => DuckDbValue::Text(text.clone()),
+        _ => DuckDbValue::Null,
+    }
+}
+
+/// List expression like `CAST([?, ?] AS VARCHAR[])` of the field of each item
+fn list_sql(value: &NotionPropertyValue, key: &str, params: &mut Vec<DuckDbValue>) -> String {
+    let items = match value {
+        NotionPropertyValue::Json(Value::Array(items)) => items,
+        _ => return "NULL".to_string(),
+    };
+    let placeholders = items
+        .iter()
+        .filter_map(|item| item.get(key).and_then(|text| text.as_str()))
+        .map(|text| {
+            params.push(DuckDbValue::Text(text.to_string()));
+            "?"
+        })
+        .collect::<Vec<_>>();
+    format!("CAST([{}] AS VARCHAR[])", placeholders.join(", "))
+}
+
+/// Struct expression of a date property like `{"start": "2022-03-20", "end": null, "time_zone": null}`
+fn date_sql(value: &NotionPropertyValue, params: &mut Vec<DuckDbValue>) -> String {
+    let date = match value {
+        NotionPropertyValue::Json(date @ Value::Object(_)) => date,
+        _ => return "NULL".to_string(),
+    };
+    for key in ["start", "end", "time_zone"] {
+        params.push(match date.get(key).and_then(|text| text.as_str()) {
+            Some(text) => DuckDbValue::Text(text.to_string()),
+            None => DuckDbValue::Null,
+        });
+    }
+    "{'start': TRY_CAST(? AS TIMESTAMP), 'end': TRY_CAST(? AS TIMESTAMP), 'time_zone': CAST(? AS VARCHAR)}"
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_list_sql() {
+        let value = NotionPropertyValue::Json(json!([
+            {"id": "1", "name": "multi", "color": "red"},
+            {"id": "2", "name": "it's", "color": "blue"},
+        ]));
+        let mut params = vec![];
+        assert_eq!(
+            list_sql(&value, "name", &mut params),
+            "CAST([?, ?] AS VARCHAR[])"
+        );
+        assert_eq!(
+            params,
+            vec![
+                DuckDbValue::Text("multi".to_string()),
+                DuckDbValue::Text("it's".to_string())
+            ]
+        );
+        let mut params = vec![];
+        let value = NotionPropertyValue::Json(json!([]));
+        assert_eq!(list_sql(&value, "id", &mut params), "CAST([] AS VARCHAR[])");
+        assert_eq!(
+            list_sql(&NotionPropertyValue::Null, "id", &mut params),
+            "NULL"
+        );
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_date_sql() {
+        let value = NotionPropertyValue::Json(
+            json!({"start": "2022-03-20", "end": null, "time_zone": null}),
+        );
+        let mut params = vec![];
+        assert_eq!(
+            date_sql(&value, &mut params),
+            "{'start': TRY_CAST(? AS TIMESTAMP), 'end': TRY_CAST(? AS TIMESTAMP), 'time_zone': CAST(? AS VARCHAR)}"
+        );
+        assert_eq!(
+            params,
+            vec![
+                DuckDbValue::Text("2022-03-20".to_string()),
+                DuckDbValue::Null,
+                DuckDbValue::Null
+            ]
+        );
+        assert_eq!(date_sql(&NotionPropertyValue::Null, &mut params), "NULL");
+        assert_eq!(params.len(), 3);
+    }
+}
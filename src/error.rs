@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::diagnostics::ParseDiagnostic;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by the library.
+/// Each variant belongs to a category which is reported by the CLI as a distinct exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid argument or configuration given by the caller
+    InvalidArgument(String),
+    /// Failed to send a request to Notion API or to read its response
+    Http(reqwest::Error),
+    /// Notion API responded with an error object.
+    /// See https://developers.notion.com/reference/errors
+    NotionApi {
+        /// HTTP status code like 401
+        status: u16,
+        /// Error code like "unauthorized" or "object_not_found"
+        code: String,
+        message: String,
+        request_id: Option<String>,
+    },
+    /// A response from Notion API had unexpected shape
+    Parse {
+        /// JSON path to the unexpected value like `results[0].url`
+        json_path: String,
+        message: String,
+    },
+    /// Some pages or properties were skipped during parsing in strict mode
+    Strict(Vec<ParseDiagnostic>),
+    /// The output path can't be used
+    Output {
+        path: String,
+        message: String,
+    },
+    Sqlite(rusqlite::Error),
+}
+
+impl Error {
+    pub fn parse(json_path: &str, message: &str) -> Error {
+        Error::Parse {
+            json_path: json_path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Exit code of the CLI for the category of the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidArgument(_) => 2,
+            Error::Http(_) => 3,
+            Error::NotionApi { .. } => 4,
+            Error::Parse { .. } | Error::Strict(_) => 5,
+            Error::Output { .. } | Error::Sqlite(_) => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            Error::Http(err) => write!(f, "Failed to request Notion API: {}", err),
+            Error::NotionApi {
+                status,
+                code,
+                message,
+                request_id,
+            } => {
+                write!(
+                    f,
+                    "Error response from Notion API: {} {}: {}",
+                    status, code, message
+                )?;
+                if let Some(request_id) = request_id {
+                    write!(f, " (request id: {})", request_id)?;
+                }
+                Ok(())
+            }
+            Error::Parse { json_path, message } => {
+                write!(
+                    f,
+                    "Unexpected response from Notion API at {}: {}",
+                    json_path, message
+                )
+            }
+            Error::Strict(diagnostics) => {
+                write!(
+                    f,
+                    "Skipped {} item(s) while parsing in strict mode",
+                    diagnostics.len()
+                )?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic)?;
+                }
+                Ok(())
+            }
+            Error::Output { path, message } => write!(f, "Invalid output {}: {}", path, message),
+            Error::Sqlite(err) => write!(f, "SQLite error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(err) => Some(err),
+            Error::Sqlite(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Sqlite(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_notion_api_error() {
+        let err = Error::NotionApi {
+            status: 404,
+            code: "object_not_found".into(),
+            message: "Could not find database".into(),
+            request_id: Some("xxxx".into()),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Error response from Notion API: 404 object_not_found: Could not find database (request id: xxxx)"
+        );
+        assert_eq!(err.exit_code(), 4);
+    }
+}
@@ -0,0 +1,719 @@
+use crate::diagnostics::ParseDiagnostics;
+use crate::diff::{diff_pages, ExistingTable, TableDiff};
+use crate::error::{Error, Result};
+use crate::notion_client::NotionClient;
+use crate::notion_comments::NotionComment;
+use crate::notion_database::{NotionDatabaseSchema, NotionParent};
+use crate::notion_pages::NotionPage;
+use crate::rate_limiter::{RateLimiter, RateLimiterMetrics};
+use crate::sqlite::properties_table_names;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where schemas and pages of databases come from.
+/// `NotionClient` is the implementation backed by Notion API.
+pub trait PageSource {
+    /// Fetch schemas of all tables which the database consists of
+    fn get_database_schemas(&self, database_id: &str) -> Result<Vec<NotionDatabaseSchema>>;
+
+    /// Fetch all pages for the schema.
+    /// Pages and properties which can't be parsed are recorded into `diagnostics`.
+    fn get_all_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>>;
+
+    /// Fetch a batch of pages starting at the cursor, with the cursor of the next batch if there are more.
+    /// Sources which can't paginate return all pages at once, and can't resume from a cursor.
+    fn get_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        match start_cursor {
+            None => Ok((self.get_all_pages(database_id, schema, diagnostics)?, None)),
+            Some(_) => Err(Error::InvalidArgument(
+                "The page source can't resume from a cursor".to_string(),
+            )),
+        }
+    }
+
+    /// Filter and sorts of the query for the schema, which a cursor is only valid with
+    fn query(&self, _schema: &NotionDatabaseSchema) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    /// Fetch comments on the page, and on blocks in it if `include_blocks` is true.
+    /// Sources which don't support comments return none.
+    fn get_comments(&self, _page_id: &str, _include_blocks: bool) -> Result<Vec<NotionComment>> {
+        Ok(vec![])
+    }
+}
+
+/// Where exported pages are written to.
+/// `SqliteSink` is the implementation which writes into a SQLite file.
+pub trait Sink {
+    /// Prepare the table for the schema. Called once for each table before its pages are written.
+    /// It may be called again for the same table when the exporter runs repeatedly.
+    fn create_table(&mut self, schema: &NotionDatabaseSchema, table_name: &str) -> Result<()>;
+
+    /// Write pages into the table. Pages which already exist should be overwritten.
+    /// It's called for each batch of pages as they are fetched, or once with all pages in strict mode.
+    fn write_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()>;
+
+    /// Delete pages which are no longer in the database from the table. Called after all pages of the table are
+    /// written, only if the sink could tell them with `existing_table()`.
+    fn delete_pages(
+        &mut self,
+        _schema: &NotionDatabaseSchema,
+        _table_name: &str,
+        _page_ids: &[String],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Record the databases which the tables are exported from. Called once after all tables are written.
+    fn write_databases(&mut self, _databases: &[DatabaseRecord]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Write comments on the exported pages. Called once after all tables are written if comments are exported,
+    /// or after each batch of pages is written if checkpoints are saved, which only happens with sinks supporting
+    /// them. Comments which already exist should be overwritten.
+    fn write_comments(&mut self, _comments: &[NotionComment]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read what the table holds to compare incoming pages with, without writing anything.
+    /// Called before the pages are written, and instead of writing them in dry-run mode.
+    /// Sinks which can't read what they wrote return None, and don't support dry run.
+    fn existing_table(&mut self, _table_name: &str) -> Result<Option<ExistingTable>> {
+        Ok(None)
+    }
+
+    /// Whether the sink keeps checkpoints and comments written batch by batch, so that exports can be resumed.
+    /// Exporters saving checkpoints can't be built with sinks which don't.
+    fn supports_checkpoints(&self) -> bool {
+        false
+    }
+
+    /// Read the checkpoint of the table saved by a previous run.
+    /// Sinks which don't keep checkpoints return None, in which case exports can't be resumed.
+    fn read_checkpoint(&mut self, _table_name: &str) -> Result<Option<Checkpoint>> {
+        Ok(None)
+    }
+
+    /// Save the checkpoint of the table. Called when the export of the table starts and after each batch of pages
+    /// is written.
+    fn write_checkpoint(&mut self, _checkpoint: &Checkpoint) -> Result<()> {
+        Ok(())
+    }
+
+    /// Delete all checkpoints. Called when an export has completed, so that a later one isn't resumed from them.
+    fn clear_checkpoints(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Record a run of the export, which may have failed. Sinks which don't keep a log ignore it.
+    fn write_sync_run(&mut self, _run: &SyncRun) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after all tables are written
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Fail without writing anything if any page or property was skipped during parsing
+    pub strict: bool,
+    /// Export comments on pages
+    pub comments: bool,
+    /// Export comments on blocks in pages as well. It's only used with `comments`.
+    pub block_comments: bool,
+    /// Only report what would change in the sink without writing anything
+    pub dry_run: bool,
+    /// Save a checkpoint after each batch of pages is written
+    pub checkpoints: bool,
+    /// Continue from the checkpoints saved by an interrupted export. It's only used with `checkpoints`.
+    pub resume: bool,
+    /// Notion-Version which the source requests with, recorded with the run
+    pub notion_version: String,
+    /// Rate limiter of the source, whose requests during the run are recorded with it
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+/// Result of a table written by `Exporter::run()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSummary {
+    pub database_id: String,
+    pub table_name: String,
+    pub pages: usize,
+}
+
+/// A database exported into a table, with where it's located in the workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseRecord {
+    pub database_id: String,
+    /// Id of the data source if the table is exported from a data source
+    pub data_source_id: Option<String>,
+    /// Title of the data source or the database
+    pub title: String,
+    pub parent: NotionParent,
+    pub table_name: String,
+}
+
+/// Progress of exporting a table, which is saved after each batch of pages to resume an interrupted export
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Id of the run which saved the checkpoint. Runs started later have larger ids.
+    pub run_id: u64,
+    pub table_name: String,
+    pub database_id: String,
+    /// Cursor of the next batch of pages. None if no batch has been written yet, or all of them have been.
+    pub next_cursor: Option<String>,
+    /// Whether all pages of the table have been written
+    pub completed: bool,
+    /// Filter and sort keys of the query, which the cursor is only valid with
+    pub query: Value,
+    /// Property names and types of the schema, to verify it hasn't changed when resuming
+    pub schema: String,
+    /// Number of pages written so far
+    pub pages: usize,
+}
+
+/// Property names and types of the schema like `[["Age","number"],["Name","title"]]`
+pub fn schema_fingerprint(schema: &NotionDatabaseSchema) -> String {
+    let mut properties = schema
+        .properties
+        .values()
+        .map(|property| (property.name.as_str(), property.property_raw_type.as_str()))
+        .collect::<Vec<_>>();
+    properties.sort();
+    serde_json::to_string(&properties).unwrap()
+}
+
+/// A run of the export recorded into the sink, such as "sync_runs" table of SQLite
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncRun {
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    /// Version of notion-into-sqlite
+    pub tool_version: String,
+    pub notion_version: String,
+    pub database_ids: Vec<String>,
+    pub pages_fetched: usize,
+    /// Numbers of pages inserted, updated and no longer in the databases.
+    /// None if the sink can't compare pages with what it holds.
+    pub pages_inserted: Option<usize>,
+    pub pages_updated: Option<usize>,
+    pub pages_deleted: Option<usize>,
+    /// Number of requests sent to Notion API, including retries
+    pub requests: u64,
+    /// Number of requests which waited for the rate limit
+    pub rate_limited_requests: u64,
+    /// Number of requests which were sent again since Notion API was rate limited or unavailable
+    pub retries: u64,
+    /// Message of the error if the run failed
+    pub error: Option<String>,
+}
+impl SyncRun {
+    /// Record of the run which started at `started_at` and has just finished with the result
+    pub fn new(
+        started_at: SystemTime,
+        database_ids: &[String],
+        result: &Result<ExportSummary>,
+    ) -> SyncRun {
+        let mut run = SyncRun {
+            started_at,
+            finished_at: SystemTime::now(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            notion_version: String::new(),
+            database_ids: database_ids.to_vec(),
+            pages_fetched: 0,
+            pages_inserted: None,
+            pages_updated: None,
+            pages_deleted: None,
+            requests: 0,
+            rate_limited_requests: 0,
+            retries: 0,
+            error: None,
+        };
+        match result {
+            Ok(summary) => {
+                run.pages_fetched = summary.tables.iter().map(|table| table.pages).sum();
+                if !summary.diffs.is_empty() {
+                    let sum = |count: fn(&TableDiff) -> usize| {
+                        Some(summary.diffs.iter().map(count).sum())
+                    };
+                    run.pages_inserted = sum(|diff| diff.inserted.len());
+                    run.pages_updated = sum(|diff| diff.updated.len());
+                    run.pages_deleted = sum(|diff| diff.deleted.len());
+                }
+            }
+            Err(err) => run.error = Some(err.to_string()),
+        }
+        run
+    }
+}
+
+/// Result of `Exporter::run()`
+#[derive(Debug)]
+pub struct ExportSummary {
+    pub tables: Vec<TableSummary>,
+    /// Number of exported comments
+    pub comments: usize,
+    pub diagnostics: ParseDiagnostics,
+    /// Changes made to each table, or which would be made in dry-run mode.
+    /// Empty if the sink can't compare pages with what it holds.
+    pub diffs: Vec<TableDiff>,
+}
+
+/// Exports databases from a page source into a sink.
+///
+/// ```no_run
+/// use notion_into_sqlite::exporter::Exporter;
+/// use notion_into_sqlite::notion_client::NotionClient;
+/// use notion_into_sqlite::sqlite::SqliteSink;
+///
+/// let mut exporter = Exporter::builder()
+///     .client(NotionClient::new("secret_xxx"))
+///     .database_id("8a281474f0714c548afc17d8a4b7c782")
+///     .sink(SqliteSink::new("notion.db"))
+///     .build()?;
+/// let summary = exporter.run()?;
+/// # Ok::<(), notion_into_sqlite::error::Error>(())
+/// ```
+pub struct Exporter {
+    source: Box<dyn PageSource>,
+    database_ids: Vec<String>,
+    sink: Box<dyn Sink>,
+    options: ExportOptions,
+}
+
+impl Exporter {
+    pub fn builder() -> ExporterBuilder {
+        ExporterBuilder::default()
+    }
+
+    /// Fetch all pages of the databases and write them into the sink.
+    /// It can be called repeatedly, in which case existing pages are overwritten, and pages which are no longer in
+    /// the databases are deleted from sinks which can tell them.
+    /// Each run is recorded into the sink with `Sink::write_sync_run()` whether it succeeds or not, except in
+    /// dry-run mode.
+    pub fn run(&mut self) -> Result<ExportSummary> {
+        let started_at = SystemTime::now();
+        let metrics_before = self.rate_limiter_metrics();
+        let result = self.export();
+        if self.options.dry_run {
+            return result;
+        }
+
+        let mut run = SyncRun::new(started_at, &self.database_ids, &result);
+        run.notion_version = self.options.notion_version.clone();
+        let metrics = self.rate_limiter_metrics();
+        run.requests = metrics.requests - metrics_before.requests;
+        run.rate_limited_requests = metrics.waited_requests - metrics_before.waited_requests;
+        run.retries = metrics.retries - metrics_before.retries;
+        match (self.sink.write_sync_run(&run), result) {
+            (Err(err), Ok(_)) => Err(err),
+            (Err(err), Err(export_err)) => {
+                warn!("Failed to record the failed run: {}", err);
+                Err(export_err)
+            }
+            (Ok(_), result) => result,
+        }
+    }
+
+    /// Metrics of the rate limiter of the source so far, which are all zero without one
+    fn rate_limiter_metrics(&self) -> RateLimiterMetrics {
+        self.options
+            .rate_limiter
+            .as_ref()
+            .map(|rate_limiter| rate_limiter.metrics())
+            .unwrap_or_default()
+    }
+
+    fn export(&mut self) -> Result<ExportSummary> {
+        let mut schemas: Vec<(String, NotionDatabaseSchema)> = vec![];
+        for database_id in &self.database_ids {
+            for schema in self.source.get_database_schemas(database_id)? {
+                schemas.push((database_id.clone(), schema));
+            }
+        }
+        let table_names = properties_table_names(schemas.iter().map(|(_, schema)| schema));
+
+        let mut output = ExportOutput::default();
+        if self.options.strict || self.options.dry_run {
+            self.write_at_once(&schemas, &table_names, &mut output)?;
+        } else {
+            self.write_in_batches(&schemas, &table_names, &mut output)?;
+        }
+
+        let mut tables = vec![];
+        let mut databases = vec![];
+        for (((database_id, schema), table_name), pages) in
+            schemas.iter().zip(table_names).zip(output.pages)
+        {
+            databases.push(DatabaseRecord {
+                database_id: database_id.clone(),
+                data_source_id: schema.data_source.as_ref().map(|d| d.id.clone()),
+                title: schema.title.clone(),
+                parent: schema.parent.clone(),
+                table_name: table_name.clone(),
+            });
+            tables.push(TableSummary {
+                database_id: database_id.clone(),
+                table_name,
+                pages,
+            });
+        }
+        if self.options.dry_run {
+            return Ok(ExportSummary {
+                tables,
+                comments: 0,
+                diagnostics: output.diagnostics,
+                diffs: output.diffs,
+            });
+        }
+        self.sink.write_databases(&databases)?;
+        // With checkpoints, comments have been written batch by batch
+        if self.options.comments && !self.options.checkpoints {
+            self.sink.write_comments(&output.comments)?;
+        }
+        self.sink.clear_checkpoints()?;
+        self.sink.finish()?;
+
+        Ok(ExportSummary {
+            tables,
+            comments: output.comments.len() + output.written_comments,
+            diagnostics: output.diagnostics,
+            diffs: output.diffs,
+        })
+    }
+
+    /// Fetch pages of all tables, and then write them unless any of them is skipped in strict mode.
+    /// Used in strict mode and dry-run mode, which need all pages before writing anything.
+    fn write_at_once(
+        &mut self,
+        schemas: &[(String, NotionDatabaseSchema)],
+        table_names: &[String],
+        output: &mut ExportOutput,
+    ) -> Result<()> {
+        let mut all_pages = vec![];
+        for (database_id, schema) in schemas {
+            let pages = self
+                .source
+                .get_all_pages(database_id, schema, &mut output.diagnostics)?;
+            all_pages.push(pages);
+        }
+        if self.options.comments && !self.options.dry_run {
+            for page in all_pages.iter().flatten() {
+                output.comments.extend(
+                    self.source
+                        .get_comments(&page.id, self.options.block_comments)?,
+                );
+            }
+        }
+        if self.options.strict && !output.diagnostics.is_empty() {
+            return Err(Error::Strict(output.diagnostics.items.clone()));
+        }
+
+        let skipped_ids = output.diagnostics.skipped_page_ids();
+        for (((_, schema), table_name), pages) in schemas.iter().zip(table_names).zip(all_pages) {
+            let existing = self.sink.existing_table(table_name)?;
+            if self.options.dry_run {
+                let existing = existing.ok_or_else(|| {
+                    Error::InvalidArgument("Dry run is not supported by the output".to_string())
+                })?;
+                output.diffs.push(diff_parsed_pages(
+                    schema,
+                    table_name,
+                    &existing,
+                    &pages,
+                    &skipped_ids,
+                ));
+            } else {
+                let diff = existing.map(|existing| {
+                    diff_parsed_pages(schema, table_name, &existing, &pages, &skipped_ids)
+                });
+                self.sink.create_table(schema, table_name)?;
+                self.sink.write_pages(schema, table_name, &pages)?;
+                if let Some(diff) = diff {
+                    self.sink.delete_pages(schema, table_name, &diff.deleted)?;
+                    output.diffs.push(diff);
+                }
+            }
+            output.pages.push(pages.len());
+        }
+        Ok(())
+    }
+
+    /// Write each batch of pages as soon as it's fetched, and save a checkpoint after it if checkpoints are enabled.
+    /// When resuming, tables continue from the checkpoints of the latest interrupted run and completed ones are
+    /// skipped. Checkpoints left by earlier runs are ignored.
+    fn write_in_batches(
+        &mut self,
+        schemas: &[(String, NotionDatabaseSchema)],
+        table_names: &[String],
+        output: &mut ExportOutput,
+    ) -> Result<()> {
+        let mut run_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut saved_checkpoints = vec![None; table_names.len()];
+        if self.options.resume {
+            for (saved, table_name) in saved_checkpoints.iter_mut().zip(table_names) {
+                *saved = self.sink.read_checkpoint(table_name)?;
+            }
+            let latest_run_id = saved_checkpoints
+                .iter()
+                .flatten()
+                .map(|saved| saved.run_id)
+                .max();
+            run_id = latest_run_id.unwrap_or(run_id);
+        }
+
+        for (((database_id, schema), table_name), saved) in
+            schemas.iter().zip(table_names).zip(saved_checkpoints)
+        {
+            let mut checkpoint = Checkpoint {
+                run_id,
+                table_name: table_name.clone(),
+                database_id: database_id.clone(),
+                next_cursor: None,
+                completed: false,
+                query: self.source.query(schema)?,
+                schema: schema_fingerprint(schema),
+                pages: 0,
+            };
+            let mut resumed = false;
+            if let Some(saved) = saved.filter(|saved| saved.run_id == run_id) {
+                if saved.database_id != checkpoint.database_id
+                    || saved.schema != checkpoint.schema
+                    || saved.query != checkpoint.query
+                {
+                    return Err(Error::InvalidArgument(format!(
+                        "Can't resume exporting {} since its schema or query has changed after the checkpoint",
+                        table_name
+                    )));
+                }
+                resumed = saved.next_cursor.is_some() || saved.completed;
+                checkpoint = saved;
+            }
+
+            let existing = self.sink.existing_table(table_name)?;
+            let mut diff = existing
+                .as_ref()
+                .map(|existing| diff_pages(schema, table_name, existing, &[]));
+            let mut fetched_ids = HashSet::new();
+            self.sink.create_table(schema, table_name)?;
+            if self.options.checkpoints && !resumed {
+                self.sink.write_checkpoint(&checkpoint)?;
+            }
+            while !checkpoint.completed {
+                let (pages, next_cursor) = self.source.get_pages(
+                    database_id,
+                    schema,
+                    checkpoint.next_cursor.as_deref(),
+                    &mut output.diagnostics,
+                )?;
+                let mut comments = vec![];
+                if self.options.comments {
+                    for page in &pages {
+                        comments.extend(
+                            self.source
+                                .get_comments(&page.id, self.options.block_comments)?,
+                        );
+                    }
+                }
+                if let (Some(diff), Some(existing)) = (&mut diff, &existing) {
+                    diff.merge(diff_pages(schema, table_name, existing, &pages));
+                }
+                self.sink.write_pages(schema, table_name, &pages)?;
+                // Comments of the batch are written before its checkpoint, so that they aren't lost when resuming
+                if self.options.checkpoints && self.options.comments {
+                    self.sink.write_comments(&comments)?;
+                    output.written_comments += comments.len();
+                } else {
+                    output.comments.extend(comments);
+                }
+
+                fetched_ids.extend(pages.iter().map(|page| page.id.clone()));
+                checkpoint.pages += pages.len();
+                checkpoint.completed = next_cursor.is_none();
+                checkpoint.next_cursor = next_cursor;
+                if self.options.checkpoints {
+                    self.sink.write_checkpoint(&checkpoint)?;
+                }
+            }
+
+            if let Some(mut diff) = diff {
+                if resumed {
+                    // Pages written before resuming aren't fetched again, so deleted pages can't be told
+                    diff.deleted.clear();
+                } else {
+                    // Pages which couldn't be parsed are kept as they are instead of being deleted
+                    let skipped_ids = output.diagnostics.skipped_page_ids();
+                    diff.deleted.retain(|page_id| {
+                        !fetched_ids.contains(page_id) && !skipped_ids.contains(page_id.as_str())
+                    });
+                }
+                self.sink.delete_pages(schema, table_name, &diff.deleted)?;
+                output.diffs.push(diff);
+            }
+            // Resumed tables count the pages written before resuming as well
+            output.pages.push(checkpoint.pages);
+        }
+        Ok(())
+    }
+}
+
+/// Diff of the pages with the table. Pages which couldn't be parsed are kept as they are instead of being deleted.
+fn diff_parsed_pages(
+    schema: &NotionDatabaseSchema,
+    table_name: &str,
+    existing: &ExistingTable,
+    pages: &[NotionPage],
+    skipped_ids: &HashSet<&str>,
+) -> TableDiff {
+    let mut diff = diff_pages(schema, table_name, existing, pages);
+    diff.deleted
+        .retain(|page_id| !skipped_ids.contains(page_id.as_str()));
+    diff
+}
+
+/// What `Exporter::run()` collects while writing tables
+#[derive(Default)]
+struct ExportOutput {
+    /// Number of pages fetched for each table
+    pages: Vec<usize>,
+    /// Comments which haven't been written yet
+    comments: Vec<NotionComment>,
+    /// Number of comments which have been written with checkpoints
+    written_comments: usize,
+    diagnostics: ParseDiagnostics,
+    diffs: Vec<TableDiff>,
+}
+
+#[derive(Default)]
+pub struct ExporterBuilder {
+    source: Option<Box<dyn PageSource>>,
+    database_ids: Vec<String>,
+    sink: Option<Box<dyn Sink>>,
+    options: ExportOptions,
+}
+
+impl ExporterBuilder {
+    /// Fetch pages with the client. Its Notion-Version and rate limiter are recorded with runs.
+    pub fn client(self, client: NotionClient) -> ExporterBuilder {
+        let notion_version = client.notion_version.clone();
+        let rate_limiter = client.rate_limiter.clone();
+        self.source(client)
+            .notion_version(&notion_version)
+            .rate_limiter(rate_limiter)
+    }
+
+    pub fn source(mut self, source: impl PageSource + 'static) -> ExporterBuilder {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Add a database to export. It can be called multiple times.
+    pub fn database_id(mut self, database_id: &str) -> ExporterBuilder {
+        self.database_ids.push(database_id.to_string());
+        self
+    }
+
+    pub fn sink(mut self, sink: impl Sink + 'static) -> ExporterBuilder {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    pub fn options(mut self, options: ExportOptions) -> ExporterBuilder {
+        self.options = options;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> ExporterBuilder {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Export comments on pages, and on blocks in them if `include_blocks` is true
+    pub fn comments(mut self, include_blocks: bool) -> ExporterBuilder {
+        self.options.comments = true;
+        self.options.block_comments = include_blocks;
+        self
+    }
+
+    /// Only report what would change in the sink without writing anything
+    pub fn dry_run(mut self, dry_run: bool) -> ExporterBuilder {
+        self.options.dry_run = dry_run;
+        self
+    }
+
+    /// Notion-Version which the source requests with, recorded with runs
+    pub fn notion_version(mut self, notion_version: &str) -> ExporterBuilder {
+        self.options.notion_version = notion_version.to_string();
+        self
+    }
+
+    /// Rate limiter of the source, whose requests during runs are recorded with them
+    pub fn rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> ExporterBuilder {
+        self.options.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Save a checkpoint after each batch of pages is written.
+    /// With `resume`, tables continue from the checkpoints saved by an interrupted export.
+    pub fn checkpoints(mut self, resume: bool) -> ExporterBuilder {
+        self.options.checkpoints = true;
+        self.options.resume = resume;
+        self
+    }
+
+    pub fn build(self) -> Result<Exporter> {
+        let source = self
+            .source
+            .ok_or_else(|| Error::InvalidArgument("Page source is not specified".to_string()))?;
+        let sink = self
+            .sink
+            .ok_or_else(|| Error::InvalidArgument("Sink is not specified".to_string()))?;
+        if self.database_ids.is_empty() {
+            return Err(Error::InvalidArgument(
+                "No database id is specified".to_string(),
+            ));
+        }
+        if self.options.checkpoints && (self.options.strict || self.options.dry_run) {
+            return Err(Error::InvalidArgument(
+                "Checkpoints can't be used in strict mode or dry-run mode".to_string(),
+            ));
+        }
+        if self.options.checkpoints && !sink.supports_checkpoints() {
+            return Err(Error::InvalidArgument(
+                "Checkpoints are not supported by the output".to_string(),
+            ));
+        }
+        Ok(Exporter {
+            source,
+            database_ids: self.database_ids,
+            sink,
+            options: self.options,
+        })
+    }
+}
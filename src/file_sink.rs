@@ -0,0 +1,199 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+use crate::exporter::{DatabaseRecord, Sink};
+use crate::notion_comments::NotionComment;
+use crate::notion_database::NotionDatabaseSchema;
+use crate::notion_pages::{NotionPage, NotionPropertyValue};
+use crate::sqlite::{
+    comment_row, database_row, page_metadata_row, ColumnNames, COMMENTS_COLUMNS, COMMENTS_TABLE,
+    DATABASES_COLUMNS, DATABASES_TABLE, PAGE_METADATA_COLUMNS, PAGE_METADATA_TABLE,
+};
+
+/// Format of the files written by `FileSink`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    /// CSV with a header row. Values which aren't text, such as multi-selects, are serialized as JSON.
+    Csv,
+    /// JSON Lines with an object per row, whose values are typed
+    Jsonl,
+}
+impl FileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Csv => "csv",
+            FileFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Sink which writes each table into its own file in a directory, like "pages.csv" and "page_metadata.csv".
+/// The tables are the same as those of `SqliteSink`.
+pub struct FileSink {
+    pub directory: PathBuf,
+    pub format: FileFormat,
+    /// Rows of the page metadata table by page id, which is shared among all page properties tables
+    page_metadata: BTreeMap<String, Vec<NotionPropertyValue>>,
+}
+impl FileSink {
+    pub fn new(directory: &str, format: FileFormat) -> FileSink {
+        FileSink {
+            directory: PathBuf::from(directory),
+            format,
+            page_metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn csv(directory: &str) -> FileSink {
+        FileSink::new(directory, FileFormat::Csv)
+    }
+
+    pub fn jsonl(directory: &str) -> FileSink {
+        FileSink::new(directory, FileFormat::Jsonl)
+    }
+
+    /// Check if files can be written into the directory. It must not exist or be empty.
+    pub fn validate_directory_path(path: &str) -> Result<()> {
+        let output_error = |message: String| Error::Output {
+            path: path.to_string(),
+            message,
+        };
+        let directory = Path::new(path);
+        if !directory.exists() {
+            return Ok(());
+        }
+        if !directory.is_dir() {
+            return Err(output_error("already exists as a file".to_string()));
+        }
+        let mut entries = fs::read_dir(directory).map_err(|err| output_error(err.to_string()))?;
+        if entries.next().is_some() {
+            return Err(output_error("already exists and is not empty".to_string()));
+        }
+        Ok(())
+    }
+
+    fn table_path(&self, table_name: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", table_name, self.format.extension()))
+    }
+
+    /// Create the file of the table, or truncate it if it exists
+    fn create_table_file(&self, table_name: &str, header: &[&str]) -> Result<()> {
+        fs::create_dir_all(&self.directory).map_err(|err| self.output_error(&err))?;
+        let path = self.table_path(table_name);
+        let file = File::create(&path).map_err(|err| self.output_error(&err))?;
+        if self.format == FileFormat::Csv {
+            let mut writer = csv::Writer::from_writer(file);
+            writer
+                .write_record(header)
+                .and_then(|_| writer.flush().map_err(csv::Error::from))
+                .map_err(|err| self.output_error(&err))?;
+        }
+        Ok(())
+    }
+
+    /// Append rows to the file of the table. Each row has values in the order of `header`.
+    fn append_rows<V: Borrow<NotionPropertyValue>>(
+        &self,
+        table_name: &str,
+        header: &[&str],
+        rows: impl IntoIterator<Item = impl AsRef<[V]>>,
+    ) -> Result<()> {
+        let path = self.table_path(table_name);
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|err| self.output_error(&err))?;
+        match self.format {
+            FileFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                for row in rows {
+                    writer
+                        .write_record(row.as_ref().iter().map(|value| value.borrow().to_text()))
+                        .map_err(|err| self.output_error(&err))?;
+                }
+                writer.flush().map_err(|err| self.output_error(&err))?;
+            }
+            FileFormat::Jsonl => {
+                let mut writer = BufWriter::new(file);
+                for row in rows {
+                    let object = header
+                        .iter()
+                        .zip(row.as_ref())
+                        .map(|(column, value)| (column.to_string(), value.borrow().to_json()))
+                        .collect::<Map<String, Value>>();
+                    writeln!(writer, "{}", Value::Object(object))
+                        .map_err(|err| self.output_error(&err))?;
+                }
+                writer.flush().map_err(|err| self.output_error(&err))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the whole table at once
+    fn write_table<V: Borrow<NotionPropertyValue>>(
+        &self,
+        table_name: &str,
+        header: &[&str],
+        rows: impl IntoIterator<Item = impl AsRef<[V]>>,
+    ) -> Result<()> {
+        self.create_table_file(table_name, header)?;
+        self.append_rows(table_name, header, rows)
+    }
+
+    fn output_error(&self, err: &dyn std::error::Error) -> Error {
+        Error::Output {
+            path: self.directory.display().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn create_table(&mut self, schema: &NotionDatabaseSchema, table_name: &str) -> Result<()> {
+        let column_names = ColumnNames::new(schema);
+        self.create_table_file(table_name, &column_names.header())
+    }
+
+    fn write_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()> {
+        let column_names = ColumnNames::new(schema);
+        let rows = pages.iter().map(|page| column_names.row(page));
+        self.append_rows(table_name, &column_names.header(), rows)?;
+
+        for page in pages {
+            self.page_metadata
+                .insert(page.id.clone(), page_metadata_row(page));
+        }
+        Ok(())
+    }
+
+    fn write_databases(&mut self, databases: &[DatabaseRecord]) -> Result<()> {
+        let rows = databases.iter().map(database_row);
+        self.write_table(DATABASES_TABLE, &DATABASES_COLUMNS, rows)
+    }
+
+    fn write_comments(&mut self, comments: &[NotionComment]) -> Result<()> {
+        let rows = comments.iter().map(comment_row);
+        self.write_table(COMMENTS_TABLE, &COMMENTS_COLUMNS, rows)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_table(
+            PAGE_METADATA_TABLE,
+            &PAGE_METADATA_COLUMNS,
+            self.page_metadata.values(),
+        )
+    }
+}
@@ -1,43 +1,49 @@
+pub mod async_notion_client;
+pub mod credentials;
+pub mod diagnostics;
+pub mod diff;
+#[cfg(feature = "duckdb")]
+pub mod duckdb_sink;
+pub mod error;
+pub mod exporter;
+pub mod file_sink;
 pub mod json_util;
 pub mod notion_client;
+pub mod notion_comments;
 pub mod notion_database;
+pub mod notion_id;
 pub mod notion_pages;
+#[cfg(feature = "parquet")]
+pub mod parquet_sink;
+#[cfg(feature = "postgres")]
+pub mod postgres_sink;
+pub mod query;
+pub mod rate_limiter;
 pub mod sqlite;
+pub mod workspace;
 
 #[macro_use]
 extern crate log;
 
+use crate::exporter::Exporter;
 use crate::notion_client::NotionClient;
-use crate::sqlite::Sqlite;
-use anyhow::{Context, Result};
-
-pub fn main(api_key: &str, database_id: &str, output: &str) -> Result<()> {
-    env_logger::init();
-
-    Sqlite::validate_database_path(output)
-        .with_context(|| format!("Failed to create a database file {}", output))?;
-
-    let client = NotionClient {
-        api_key: api_key.into(),
-    };
-
-    let schema = client
-        .get_database(database_id)
-        .with_context(|| "Failed to fetch database schema")?;
-    let pages = client
-        .get_all_pages(database_id, &schema)
-        .with_context(|| "Failed to fetch pages")?;
-
-    let sqlite = Sqlite::new(output, &schema).with_context(|| "Failed to connect to sqlite")?;
-    sqlite
-        .create_tables()
-        .with_context(|| "Failed to create tables")?;
-
-    for page in pages {
-        sqlite
-            .insert(&page)
-            .with_context(|| "Failed to insert pages to sqlite")?;
+use crate::sqlite::{Sqlite, SqliteSink};
+
+/// Export the database into a new SQLite file.
+///
+/// Use `exporter::Exporter` for more control, e.g. another Notion-Version, strict parsing or a custom sink.
+pub fn main(api_key: &str, database_id: &str, output: &str) -> error::Result<()> {
+    Sqlite::validate_database_path(output)?;
+
+    let mut exporter = Exporter::builder()
+        .client(NotionClient::new(api_key))
+        .database_id(database_id)
+        .sink(SqliteSink::new(output))
+        .build()?;
+    let summary = exporter.run()?;
+
+    if !summary.diagnostics.is_empty() {
+        warn!("{}", summary.diagnostics.summary());
     }
-
     Ok(())
 }
@@ -1,28 +1,525 @@
 extern crate notion_into_sqlite;
-use anyhow::Result;
-use clap::Parser;
+#[macro_use]
+extern crate log;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{ArgEnum, Parser, Subcommand};
+use notion_into_sqlite::async_notion_client::{AsyncNotionClient, AsyncPageSource};
+use notion_into_sqlite::credentials::{default_config_path, redact, resolve_api_key};
+use notion_into_sqlite::diff::TableDiff;
+#[cfg(feature = "duckdb")]
+use notion_into_sqlite::duckdb_sink::DuckDbSink;
+use notion_into_sqlite::error::{Error, Result};
+use notion_into_sqlite::exporter::Exporter;
+use notion_into_sqlite::file_sink::FileSink;
+use notion_into_sqlite::notion_client::{NotionClient, DEFAULT_NOTION_VERSION};
+#[cfg(feature = "parquet")]
+use notion_into_sqlite::parquet_sink::ParquetSink;
+#[cfg(feature = "postgres")]
+use notion_into_sqlite::postgres_sink::PostgresSink;
+use notion_into_sqlite::query::{QueryFilter, QueryOptions, QuerySort};
+use notion_into_sqlite::rate_limiter::RateLimiter;
+use notion_into_sqlite::sqlite::{
+    create_table_statements, properties_table_names, sqlite_type, ColumnNames, Sqlite, SqliteSink,
+};
+use notion_into_sqlite::workspace::find_databases;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// Notion API key
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Notion API key. Prefer NOTION_API_KEY or --api-key-file,
+    /// since the key given here shows up in shell history and process list.
+    #[clap(long, global = true)]
+    api_key: Option<String>,
+
+    /// Read Notion API key from the file
+    #[clap(long, global = true, conflicts_with = "api-key")]
+    api_key_file: Option<PathBuf>,
+
+    /// Config file which may contain credentials.
+    /// Defaults to ~/.config/notion-into-sqlite/config.toml
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Notion database to export, given as its id, its URL or its title
+    #[clap(long)]
+    database_id: Option<String>,
+
+    /// Export all databases found under the page or the database given with --page-id or --database-id,
+    /// following child pages, inline databases and pages of the databases
+    #[clap(long)]
+    recursive: bool,
+
+    /// Page to start a recursive export from, given as its id or its URL
+    #[clap(long, requires = "recursive", conflicts_with = "database-id")]
+    page_id: Option<String>,
+
+    /// Output format. csv, jsonl and parquet write a file per table into the output directory.
+    /// parquet and duckdb are available when built with the features of the same names.
+    #[clap(long, arg_enum, default_value = "sqlite")]
+    format: OutputFormat,
+
+    /// Output path of sqlite or duckdb database, or output directory for the others.
+    /// An existing SQLite file is synced with the databases.
+    /// Defaults to notion.db for sqlite, notion.duckdb for duckdb and notion for the others.
+    /// A URL like postgres://user@localhost/notion writes into PostgreSQL with the postgres feature.
+    #[clap(long)]
+    output: Option<String>,
+
+    /// Notion API version sent as Notion-Version header.
+    /// With 2025-09-03 or later, each data source of the database is exported into its own table.
+    #[clap(long, global = true, default_value = DEFAULT_NOTION_VERSION)]
+    notion_version: String,
+
+    /// Fail without writing anything if any page or property can't be parsed
+    #[clap(long)]
+    strict: bool,
+
+    /// Maximum number of requests per second sent to Notion API. 0 disables the limit.
+    #[clap(long, global = true, default_value = "3")]
+    max_rps: f64,
+
+    /// Number of pages requested at once, from 1 to 100
+    #[clap(long, default_value = "100")]
+    page_size: u32,
+
+    /// Send up to this many requests at the same time, e.g. for truncated property values and block comments.
+    /// Requests are still limited by --max-rps.
+    #[clap(long)]
+    concurrency: Option<usize>,
+
+    /// Export only pages matching the filter.
+    /// Either a filter object of Notion API in JSON, or an expression like "Status = Done and Priority >= 2".
     #[clap(long)]
-    api_key: String,
+    filter: Option<String>,
+
+    /// Read the filter from the file instead of --filter
+    #[clap(long, conflicts_with = "filter")]
+    filter_file: Option<String>,
+
+    /// Sort pages by the property or "created_time" / "last_edited_time",
+    /// optionally followed by ":asc" or ":desc" like "Due:desc". It can be repeated.
+    #[clap(long = "sort")]
+    sorts: Vec<String>,
 
-    /// Notion database ID
+    /// Export comments on pages into "comments" table. It takes a request for each page.
     #[clap(long)]
-    database_id: String,
+    comments: bool,
 
-    /// Output path of sqlite database
-    #[clap(long, default_value = "notion.db")]
-    output: String,
+    /// Export comments on blocks in pages as well, which takes a request for each block
+    #[clap(long, requires = "comments")]
+    block_comments: bool,
+
+    /// Fetch pages and report what would change in the existing SQLite file without writing anything
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Keep every version of pages in history tables like "pages_history", which are appended on each sync
+    #[clap(long)]
+    history: bool,
+
+    /// Save a checkpoint into "checkpoints" table of the SQLite file after each batch of pages is written,
+    /// so that the export can be continued with --resume if it's interrupted
+    #[clap(long, conflicts_with_all = &["strict", "dry-run"])]
+    checkpoint: bool,
+
+    /// Continue an interrupted export into the SQLite file from the checkpoints saved with --checkpoint.
+    /// Tables which have been exported completely are skipped.
+    #[clap(long, conflicts_with_all = &["strict", "dry-run"])]
+    resume: bool,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Sqlite,
+    Csv,
+    Jsonl,
+    #[cfg(feature = "parquet")]
+    Parquet,
+    #[cfg(feature = "duckdb")]
+    Duckdb,
 }
+impl OutputFormat {
+    fn default_output(&self) -> &'static str {
+        match self {
+            OutputFormat::Sqlite => "notion.db",
+            #[cfg(feature = "duckdb")]
+            OutputFormat::Duckdb => "notion.duckdb",
+            _ => "notion",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List databases shared with the integration
+    ListDatabases {
+        /// Print as JSON instead of a table
+        #[clap(long)]
+        json: bool,
+
+        /// List only databases whose title matches the query
+        #[clap(long)]
+        query: Option<String>,
+    },
+    /// Print the schema of a database and the CREATE TABLE statements of SQLite output without fetching pages
+    Schema {
+        /// Notion database, given as its id, its URL or its title
+        #[clap(long)]
+        database_id: String,
+
+        /// Print as JSON instead of text
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+fn main() {
+    env_logger::init();
 
-fn main() -> Result<()> {
     let args = Args::parse();
-    let api_key = args.api_key;
-    let database_id = args.database_id;
-    let output = args.output;
+    if let Err(err) = run(args) {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    match &args.command {
+        Some(Command::ListDatabases { json, query }) => {
+            list_databases(&args, *json, query.as_deref())
+        }
+        Some(Command::Schema { database_id, json }) => print_schema(&args, database_id, *json),
+        None => export(&args),
+    }
+}
+
+fn export(args: &Args) -> Result<()> {
+    let output = args
+        .output
+        .as_deref()
+        .unwrap_or_else(|| args.format.default_output());
+    let postgres = is_postgres_url(output);
+    if postgres && args.format != OutputFormat::Sqlite {
+        return Err(Error::InvalidArgument(
+            "--format can't be used with a PostgreSQL URL as --output".to_string(),
+        ));
+    }
+    let sqlite_only = [
+        ("--dry-run", args.dry_run),
+        ("--history", args.history),
+        ("--checkpoint", args.checkpoint),
+        ("--resume", args.resume),
+    ];
+    for (flag, _) in sqlite_only.iter().filter(|(_, enabled)| *enabled) {
+        if postgres || args.format != OutputFormat::Sqlite {
+            return Err(Error::InvalidArgument(format!(
+                "{} is only supported for sqlite output",
+                flag
+            )));
+        }
+    }
+    match args.format {
+        // Dry run reads the existing file, history is appended to it, and resuming continues writing it
+        OutputFormat::Sqlite if args.dry_run || args.history || args.resume => {}
+        _ if postgres => validate_postgres_url(output)?,
+        // An existing file is synced with the databases
+        OutputFormat::Sqlite if Path::new(output).exists() => {}
+        OutputFormat::Sqlite => Sqlite::validate_database_path(output)?,
+        #[cfg(feature = "duckdb")]
+        OutputFormat::Duckdb => DuckDbSink::validate_database_path(output)?,
+        _ => FileSink::validate_directory_path(output)?,
+    }
+
+    let query = query_options(args)?;
+    let mut client = notion_client(args)?;
+    client.page_size = args.page_size;
+    client.query = query;
+    let rate_limiter = client.rate_limiter.clone();
+    let database_ids = match (&args.page_id, &args.database_id) {
+        (Some(page_id), _) => find_database_ids(&client, page_id)?,
+        (None, Some(database)) => {
+            let database_id = client.resolve_database_id(database)?;
+            if args.recursive {
+                find_database_ids(&client, &database_id)?
+            } else {
+                vec![database_id]
+            }
+        }
+        (None, None) => {
+            return Err(Error::InvalidArgument(
+                "--database-id is required".to_string(),
+            ))
+        }
+    };
+
+    let mut builder = match args.concurrency {
+        Some(concurrency) => {
+            let client = AsyncNotionClient::from_client(&client, concurrency);
+            Exporter::builder()
+                .notion_version(&client.notion_version)
+                .rate_limiter(client.rate_limiter.clone())
+                .source(AsyncPageSource::new(client))
+        }
+        None => Exporter::builder().client(client),
+    };
+    for database_id in &database_ids {
+        builder = builder.database_id(database_id);
+    }
+    builder = match args.format {
+        #[cfg(feature = "postgres")]
+        _ if postgres => builder.sink(PostgresSink::new(output)),
+        OutputFormat::Sqlite => builder.sink(SqliteSink::new(output).history(args.history)),
+        OutputFormat::Csv => builder.sink(FileSink::csv(output)),
+        OutputFormat::Jsonl => builder.sink(FileSink::jsonl(output)),
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => builder.sink(ParquetSink::new(output)),
+        #[cfg(feature = "duckdb")]
+        OutputFormat::Duckdb => builder.sink(DuckDbSink::new(output)),
+    };
+    builder = builder.strict(args.strict).dry_run(args.dry_run);
+    if args.checkpoint || args.resume {
+        builder = builder.checkpoints(args.resume);
+    }
+    if args.comments {
+        builder = builder.comments(args.block_comments);
+    }
+    let summary = builder.build()?.run()?;
+    if args.dry_run {
+        print_diffs(&summary.diffs, output);
+    } else if args.comments {
+        eprintln!("Exported {} comment(s)", summary.comments);
+    }
+
+    let metrics = rate_limiter.metrics();
+    info!(
+        "Sent {} requests including {} retries. {} of them waited for rate limit, {:?} in total and {:?} at most.",
+        metrics.requests,
+        metrics.retries,
+        metrics.waited_requests,
+        metrics.total_wait,
+        metrics.max_wait
+    );
+    if !summary.diagnostics.is_empty() {
+        eprintln!("{}", summary.diagnostics.summary());
+    }
+    Ok(())
+}
+
+/// Print changes which the export would make, with ids of the pages
+fn print_diffs(diffs: &[TableDiff], output: &str) {
+    println!("Dry run: nothing was written to {}", output);
+    for diff in diffs {
+        let new_table = if diff.new_table { " (new table)" } else { "" };
+        println!(
+            "{}{}: {} to insert, {} to update, {} to delete, {} unchanged",
+            diff.table_name,
+            new_table,
+            diff.inserted.len(),
+            diff.updated.len(),
+            diff.deleted.len(),
+            diff.unchanged
+        );
+        for column in &diff.added_columns {
+            println!("  column to add: {}", column);
+        }
+        for page_id in &diff.inserted {
+            println!("  + {}", page_id);
+        }
+        for page_id in &diff.updated {
+            println!("  ~ {}", page_id);
+        }
+        for page_id in &diff.deleted {
+            println!("  - {}", page_id);
+        }
+    }
+}
+
+/// Ids of all databases under the page or the database
+fn find_database_ids(client: &NotionClient, root_id: &str) -> Result<Vec<String>> {
+    let databases = find_databases(client, root_id)?;
+    eprintln!("Found {} database(s)", databases.len());
+    Ok(databases.into_iter().map(|database| database.id).collect())
+}
+
+fn is_postgres_url(output: &str) -> bool {
+    output.starts_with("postgres://") || output.starts_with("postgresql://")
+}
+
+#[cfg(feature = "postgres")]
+fn validate_postgres_url(url: &str) -> Result<()> {
+    PostgresSink::validate_url(url)
+}
+
+#[cfg(not(feature = "postgres"))]
+fn validate_postgres_url(_url: &str) -> Result<()> {
+    Err(Error::InvalidArgument(
+        "PostgreSQL output is available when built with the postgres feature".to_string(),
+    ))
+}
+
+fn list_databases(args: &Args, json: bool, query: Option<&str>) -> Result<()> {
+    let client = notion_client(args)?;
+    let databases = client.search_databases(query)?;
+
+    if json {
+        let databases = databases
+            .iter()
+            .map(|database| {
+                serde_json::json!({
+                    "id": database.id,
+                    "title": database.title,
+                    "parent": database.parent,
+                    "last_edited_time": database.last_edited_time,
+                    "url": database.url,
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&databases).unwrap());
+        return Ok(());
+    }
+
+    let header = ["ID", "TITLE", "PARENT", "LAST EDITED"];
+    let rows = databases
+        .iter()
+        .map(|database| {
+            [
+                database.id.as_str(),
+                database.title.as_str(),
+                database.parent.as_str(),
+                database.last_edited_time.as_str(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(header, &rows);
+    Ok(())
+}
+
+fn print_schema(args: &Args, database: &str, json: bool) -> Result<()> {
+    let client = notion_client(args)?;
+    let database_id = client.resolve_database_id(database)?;
+    let schemas = client.get_database_schemas(&database_id)?;
+    let table_names = properties_table_names(&schemas);
+
+    let tables = schemas
+        .iter()
+        .zip(&table_names)
+        .map(|(schema, table_name)| {
+            let column_names = ColumnNames::new(schema);
+            let properties = column_names
+                .property_names()
+                .into_iter()
+                .map(|name| {
+                    let property = &schema.properties[name];
+                    serde_json::json!({
+                        "name": name,
+                        "type": property.property_raw_type,
+                        "column": column_names.resolve(name),
+                        "sqlite_type": sqlite_type(&property.property_type),
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::json!({
+                "table_name": table_name,
+                "id": schema.id,
+                "title": schema.title,
+                "properties": properties,
+                "statements": create_table_statements(schema, table_name),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tables).unwrap());
+        return Ok(());
+    }
+
+    for (i, (schema, table_name)) in schemas.iter().zip(&table_names).enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "Table {} from \"{}\" ({})",
+            table_name, schema.title, schema.id
+        );
+        println!();
+        let column_names = ColumnNames::new(schema);
+        let rows = column_names
+            .property_names()
+            .into_iter()
+            .map(|name| {
+                let property = &schema.properties[name];
+                [
+                    name,
+                    property.property_raw_type.as_str(),
+                    column_names.resolve(name),
+                    sqlite_type(&property.property_type),
+                ]
+            })
+            .collect::<Vec<_>>();
+        print_table(["PROPERTY", "TYPE", "COLUMN", "SQLITE TYPE"], &rows);
+        for sql in create_table_statements(schema, table_name) {
+            println!();
+            println!("{};", sql);
+        }
+    }
+    Ok(())
+}
+
+/// Print rows as columns aligned with the header
+fn print_table<const N: usize>(header: [&str; N], rows: &[[&str; N]]) {
+    let mut widths = header.map(|column| column.chars().count());
+    for row in rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{:width$}", column, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Client configured with options common to all commands
+fn notion_client(args: &Args) -> Result<NotionClient> {
+    let config_path = args.config.clone().or_else(default_config_path);
+    let (api_key, source) = resolve_api_key(
+        args.api_key.as_deref(),
+        args.api_key_file.as_deref(),
+        config_path.as_deref(),
+    )?;
+    info!("Using Notion API key {} from {}", redact(&api_key), source);
+    let mut client = NotionClient::new(&api_key);
+    client.notion_version = args.notion_version.clone();
+    client.rate_limiter = Arc::new(RateLimiter::new(args.max_rps));
+    Ok(client)
+}
 
-    notion_into_sqlite::main(&api_key, &database_id, &output)
+fn query_options(args: &Args) -> Result<QueryOptions> {
+    let filter = match (&args.filter, &args.filter_file) {
+        (Some(filter), _) => Some(filter.parse::<QueryFilter>()?),
+        (None, Some(path)) => {
+            let filter = fs::read_to_string(path).map_err(|err| {
+                Error::InvalidArgument(format!("Failed to read filter file {}: {}", path, err))
+            })?;
+            Some(filter.trim().parse::<QueryFilter>()?)
+        }
+        (None, None) => None,
+    };
+    let sorts = args
+        .sorts
+        .iter()
+        .map(|sort| sort.parse::<QuerySort>())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(QueryOptions { filter, sorts })
 }
@@ -1,75 +1,143 @@
-use anyhow::{anyhow, Result};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use reqwest::Method;
 use serde_json::{json, Value};
 
+use crate::credentials::redact;
+use crate::diagnostics::ParseDiagnostics;
+use crate::error::{Error, Result};
+use crate::exporter::PageSource;
 use crate::json_util::{dig_json, JsonKey};
-use crate::notion_database::{parse_database_schema, NotionDatabaseSchema};
-use crate::notion_pages::{parse_notion_page_list, NotionPage};
+use crate::notion_comments::{parse_comments, NotionComment};
+use crate::notion_database::{
+    parse_data_sources, parse_database_schema, parse_database_search_results, NotionDatabaseSchema,
+    NotionDatabaseSummary,
+};
+use crate::notion_id::{is_id_or_url, normalize_notion_id};
+use crate::notion_pages::{
+    complete_property_value, find_truncated_properties, get_next_cursor,
+    parse_notion_page_list_with_diagnostics, NotionPage,
+};
+use crate::query::QueryOptions;
+use crate::rate_limiter::{RateLimiter, DEFAULT_REQUESTS_PER_SECOND};
+
+/// Notion-Version used when nothing is specified
+pub static DEFAULT_NOTION_VERSION: &str = "2022-02-22";
+/// The first Notion-Version in which databases are split into data sources
+pub static DATA_SOURCES_NOTION_VERSION: &str = "2025-09-03";
+pub static NOTION_API_BASE_URL: &str = "https://api.notion.com/v1";
+/// The largest page size Notion API accepts
+pub static MAX_PAGE_SIZE: u32 = 100;
+/// Page size used when nothing is specified, which minimizes the number of requests
+pub static DEFAULT_PAGE_SIZE: u32 = MAX_PAGE_SIZE;
+/// Number of times a request is sent again when Notion API is rate limited or unavailable
+pub static DEFAULT_MAX_RETRIES: u32 = 3;
+/// Wait before the first retry, which doubles on each retry unless Notion API tells how long to wait
+pub static DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
 
 pub struct NotionClient {
     pub api_key: String,
+    /// Value of Notion-Version header like "2022-02-22"
+    pub notion_version: String,
+    /// Base URL of Notion API. It can be replaced with a mock server in tests.
+    pub base_url: String,
+    /// Number of pages requested at once, from 1 to `MAX_PAGE_SIZE`
+    pub page_size: u32,
+    /// Filter and sorts of queries of pages
+    pub query: QueryOptions,
+    /// Rate limiter which all requests go through. It can be shared with other clients.
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Number of times a request is sent again on 429 or 5xx responses
+    pub max_retries: u32,
+    /// Wait before the first retry when the response has no Retry-After header
+    pub retry_backoff: Duration,
+    http: reqwest::blocking::Client,
+}
+
+/// The API key is redacted so that the client can be logged
+impl fmt::Debug for NotionClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotionClient")
+            .field("api_key", &redact(&self.api_key))
+            .field("notion_version", &self.notion_version)
+            .field("base_url", &self.base_url)
+            .finish()
+    }
 }
 
 impl NotionClient {
-    pub fn get_database(&self, database_id: &str) -> Result<NotionDatabaseSchema> {
-        let url = format!("https://api.notion.com/v1/databases/{0}", database_id);
-        let client = reqwest::blocking::Client::new();
-        info!("Requesting database schema. URL: {}", &url);
-        let resp = client
-            .get(url)
-            .header("Authorization", "Bearer ".to_string() + &self.api_key)
-            .header("Notion-Version", "2022-02-22")
-            .send()?
-            .json::<Value>()?;
-        info!("Request done.");
+    pub fn new(api_key: &str) -> NotionClient {
+        NotionClient {
+            api_key: api_key.into(),
+            notion_version: DEFAULT_NOTION_VERSION.into(),
+            base_url: NOTION_API_BASE_URL.into(),
+            page_size: DEFAULT_PAGE_SIZE,
+            query: QueryOptions::default(),
+            rate_limiter: Arc::new(RateLimiter::new(DEFAULT_REQUESTS_PER_SECOND)),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            http: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Whether the API version models databases as a set of data sources.
+    pub fn uses_data_sources(&self) -> bool {
+        uses_data_sources(&self.notion_version)
+    }
+
+    /// Fetch schemas of all tables which the database consists of.
+    /// Legacy API versions return a single schema, while newer ones return one schema per data source.
+    pub fn get_database_schemas(&self, database_id: &str) -> Result<Vec<NotionDatabaseSchema>> {
+        if !self.uses_data_sources() {
+            return Ok(vec![self.get_database(database_id)?]);
+        }
+
+        let resp = self.get_database_object(database_id)?;
 
-        self.validate_response(&resp)?;
+        let data_sources = parse_data_sources(&resp)?;
+        info!("Data sources: {:?}", data_sources);
+        data_sources
+            .iter()
+            .map(|data_source| self.get_data_source(&data_source.id))
+            .collect()
+    }
 
+    pub fn get_database(&self, database_id: &str) -> Result<NotionDatabaseSchema> {
+        let resp = self.get_database_object(database_id)?;
         let schema = parse_database_schema(&resp)?;
         info!("Database schema: {:?}", schema);
         Ok(schema)
     }
 
+    pub fn get_data_source(&self, data_source_id: &str) -> Result<NotionDatabaseSchema> {
+        let url = data_source_url(&self.base_url, data_source_id)?;
+        info!("Requesting data source schema. URL: {}", &url);
+        let resp = self.send(self.request(Method::GET, &url)?)?;
+        info!("Request done.");
+
+        let schema = parse_database_schema(&resp)?;
+        info!("Data source schema: {:?}", schema);
+        Ok(schema)
+    }
+
+    /// Fetch all pages of the database.
+    /// If the schema belongs to a data source, pages are queried from the data source instead.
+    /// Pages and properties which can't be parsed are recorded into `diagnostics`.
     pub fn get_all_pages(
         &self,
         database_id: &str,
         schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
     ) -> Result<Vec<NotionPage>> {
-        let url = format!("https://api.notion.com/v1/databases/{0}/query", database_id);
-        let client = reqwest::blocking::Client::new();
-
         let mut next_cursor: Option<String> = None;
         let mut all_pages: Vec<NotionPage> = vec![];
         loop {
-            let mut query = json!({
-                "page_size": 10i32,
-                "sorts": [{
-                    "timestamp": "created_time",
-                    "direction": "ascending",
-                }]
-            });
-            if let Some(cursor) = (&next_cursor).as_ref() {
-                query
-                    .as_object_mut()
-                    .unwrap()
-                    .insert("start_cursor".into(), cursor.clone().into());
-            }
-            let query_str = query.to_string();
-
-            info!("Requesting query: URL: {}, query: {}", &url, &query_str);
-            let resp = client
-                .post(&url)
-                .header("Authorization", "Bearer ".to_string() + &self.api_key)
-                .header("Notion-Version", "2022-02-22")
-                .header("Content-Type", "application/json")
-                .body(query_str)
-                .send()?
-                .json::<Value>()?;
-            info!("Request done.");
-
-            self.validate_response(&resp)?;
-
-            let (mut pages, _next_cursor) = parse_notion_page_list(schema, &resp)?;
-            info!("Pages: {:?}", pages.len());
+            let (mut pages, _next_cursor) =
+                self.get_pages(database_id, schema, next_cursor.as_deref(), diagnostics)?;
             all_pages.append(&mut pages);
             next_cursor = _next_cursor;
 
@@ -84,16 +152,627 @@ impl NotionClient {
         Ok(all_pages)
     }
 
-    fn validate_response(&self, resp: &Value) -> Result<()> {
-        let json_keys = vec![JsonKey::String("object")];
-        let object_field = dig_json(resp, &json_keys)
-            .and_then(|o| o.as_str())
-            .ok_or_else(|| anyhow!("Unexpected response from Notion API: {}", resp))?;
+    /// Fetch a batch of pages of the database starting at the cursor,
+    /// with the cursor of the next batch if there are more pages.
+    pub fn get_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        validate_page_size(self.page_size)?;
+        let database_id = normalize_notion_id(database_id)?;
+        let url = query_url(&self.base_url, &database_id, schema);
+        let query = self.query.to_json(schema)?;
+        let query_str = query_body(&query, self.page_size, start_cursor).to_string();
+
+        info!("Requesting query: URL: {}, query: {}", &url, &query_str);
+        let mut resp = self.send(
+            self.request(Method::POST, &url)?
+                .header("Content-Type", "application/json")
+                .body(query_str),
+        )?;
+        info!("Request done.");
+        self.complete_truncated_properties(&mut resp)?;
 
-        if object_field == "error" {
-            Err(anyhow!("Error response from Notion API: {}", resp,))
+        let (pages, next_cursor) =
+            parse_notion_page_list_with_diagnostics(schema, &resp, diagnostics)?;
+        info!("Pages: {:?}", pages.len());
+        Ok((pages, next_cursor))
+    }
+
+    /// Fetch ids of all pages of the database, without the filter of `query` and without parsing properties.
+    /// It's used to walk pages in databases rather than to export them.
+    pub fn get_page_ids(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+    ) -> Result<Vec<String>> {
+        validate_page_size(self.page_size)?;
+        let database_id = normalize_notion_id(database_id)?;
+        let url = query_url(&self.base_url, &database_id, schema);
+
+        let mut next_cursor: Option<String> = None;
+        let mut page_ids: Vec<String> = vec![];
+        loop {
+            let query_str =
+                query_body(&json!({}), self.page_size, next_cursor.as_deref()).to_string();
+            info!("Requesting page ids: URL: {}, query: {}", &url, &query_str);
+            let resp = self.send(
+                self.request(Method::POST, &url)?
+                    .header("Content-Type", "application/json")
+                    .body(query_str),
+            )?;
+            let mut results = vec![];
+            next_cursor = collect_results(resp, &mut results)?;
+            page_ids.extend(
+                results
+                    .iter()
+                    .filter_map(|page| page.get("id").and_then(|id| id.as_str()))
+                    .map(|id| id.to_string()),
+            );
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(page_ids)
+    }
+
+    /// Fetch the database object.
+    /// If the id turns out to be of a page, it's reported as an invalid argument instead of a not found error.
+    fn get_database_object(&self, database_id: &str) -> Result<Value> {
+        let url = database_url(&self.base_url, database_id)?;
+        info!("Requesting database. URL: {}", &url);
+        match self.send(self.request(Method::GET, &url)?) {
+            Err(err) if is_not_found(&err) => {
+                let url = page_url(&self.base_url, database_id)?;
+                match self.send(self.request(Method::GET, &url)?) {
+                    Ok(_) => Err(page_is_given_error(&normalize_notion_id(database_id)?)),
+                    Err(_) => Err(err),
+                }
+            }
+            result => {
+                info!("Request done.");
+                result
+            }
+        }
+    }
+
+    /// Search databases shared with the integration. `query` narrows them down by title.
+    /// With data sources, each database is listed once even if it has many data sources.
+    pub fn search_databases(&self, query: Option<&str>) -> Result<Vec<NotionDatabaseSummary>> {
+        validate_page_size(self.page_size)?;
+        let url = format!("{0}/search", self.base_url);
+        let object = if self.uses_data_sources() {
+            "data_source"
         } else {
-            Ok(())
+            "database"
+        };
+
+        let mut next_cursor: Option<String> = None;
+        let mut all_databases: Vec<NotionDatabaseSummary> = vec![];
+        loop {
+            let mut body = json!({
+                "filter": {"property": "object", "value": object},
+                "page_size": self.page_size,
+            });
+            if let Some(query) = query {
+                body["query"] = query.into();
+            }
+            if let Some(cursor) = &next_cursor {
+                body["start_cursor"] = cursor.as_str().into();
+            }
+
+            info!("Requesting search: URL: {}, query: {}", &url, &body);
+            let resp = self.send(
+                self.request(Method::POST, &url)?
+                    .header("Content-Type", "application/json")
+                    .body(body.to_string()),
+            )?;
+            let (databases, _next_cursor) = parse_database_search_results(&resp)?;
+            for database in databases {
+                if !all_databases.iter().any(|d| d.id == database.id) {
+                    all_databases.push(database);
+                }
+            }
+            next_cursor = _next_cursor;
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(all_databases)
+    }
+
+    /// Resolve the database id from an id, a URL of the database, or its title.
+    /// Ids and URLs are normalized into dashed ids without requests.
+    /// A title must match exactly one database shared with the integration.
+    pub fn resolve_database_id(&self, database: &str) -> Result<String> {
+        if is_id_or_url(database) {
+            return normalize_notion_id(database);
+        }
+
+        let title = database.trim();
+        let matched = self
+            .search_databases(Some(title))?
+            .into_iter()
+            .filter(|d| d.title.trim().eq_ignore_ascii_case(title))
+            .collect::<Vec<_>>();
+        match matched.as_slice() {
+            [database] => {
+                info!(r#"Database "{}" is resolved to {}"#, title, database.id);
+                Ok(database.id.clone())
+            }
+            [] => Err(Error::InvalidArgument(format!(
+                r#"No database titled "{}" is shared with the integration"#,
+                title
+            ))),
+            _ => Err(Error::InvalidArgument(format!(
+                r#"{} databases are titled "{}". Specify one of them by id: {}"#,
+                matched.len(),
+                title,
+                matched
+                    .iter()
+                    .map(|d| d.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Fetch the block. Pages and databases are blocks as well, typed "child_page" and "child_database".
+    pub fn get_block(&self, block_id: &str) -> Result<Value> {
+        let url = format!(
+            "{0}/blocks/{1}",
+            self.base_url,
+            normalize_notion_id(block_id)?
+        );
+        info!("Requesting block. URL: {}", &url);
+        self.send(self.request(Method::GET, &url)?)
+    }
+
+    /// Fetch all child blocks of the block or the page
+    pub fn get_block_children(&self, block_id: &str) -> Result<Vec<Value>> {
+        let url = block_children_url(&self.base_url, block_id)?;
+        info!("Requesting block children. URL: {}", &url);
+        self.get_paginated(&url)
+    }
+
+    /// Fetch all items of a page property.
+    /// See https://developers.notion.com/reference/retrieve-a-page-property
+    pub fn get_property_items(&self, page_id: &str, property_id: &str) -> Result<Vec<Value>> {
+        let url = property_items_url(&self.base_url, page_id, property_id)?;
+        info!("Requesting property items. URL: {}", &url);
+        self.get_paginated(&url)
+    }
+
+    /// Fetch all comments on the page. With `include_blocks`, comments on blocks in the page are fetched as well,
+    /// which takes a request for each block with children.
+    /// Child pages and child databases are not walked since they are pages of their own.
+    pub fn get_comments(&self, page_id: &str, include_blocks: bool) -> Result<Vec<NotionComment>> {
+        let page_id = normalize_notion_id(page_id)?;
+        let mut comments = self.get_block_comments(&page_id, &page_id)?;
+        if !include_blocks {
+            return Ok(comments);
+        }
+
+        let mut parents = vec![page_id.clone()];
+        while let Some(parent_id) = parents.pop() {
+            for block in self.get_block_children(&parent_id)? {
+                let block_id = match block.get("id").and_then(|id| id.as_str()) {
+                    Some(block_id) => block_id.to_string(),
+                    None => continue,
+                };
+                let block_type = block.get("type").and_then(|t| t.as_str());
+                if matches!(block_type, Some("child_page") | Some("child_database")) {
+                    continue;
+                }
+                comments.extend(self.get_block_comments(&page_id, &block_id)?);
+                if block.get("has_children").and_then(|h| h.as_bool()) == Some(true) {
+                    parents.push(block_id);
+                }
+            }
+        }
+        Ok(comments)
+    }
+
+    /// Fetch comments on the block, which is the page itself or a block in it
+    fn get_block_comments(&self, page_id: &str, block_id: &str) -> Result<Vec<NotionComment>> {
+        let url = format!(
+            "{0}/comments?block_id={1}",
+            self.base_url,
+            normalize_notion_id(block_id)?
+        );
+        info!("Requesting comments. URL: {}", &url);
+        let results = self.get_paginated(&url)?;
+        parse_comments(&results, page_id)
+    }
+
+    /// Replace property values truncated by the query endpoint with all of their items
+    fn complete_truncated_properties(&self, query_resp: &mut Value) -> Result<()> {
+        for truncated in find_truncated_properties(query_resp) {
+            let items = self.get_property_items(&truncated.page_id, &truncated.property_id)?;
+            complete_property_value(query_resp, &truncated, &items);
+        }
+        Ok(())
+    }
+
+    /// Follow `next_cursor` of a paginated GET endpoint and collect all results.
+    /// A response which isn't a list, e.g. a single property item, is returned as the only item.
+    fn get_paginated(&self, url: &str) -> Result<Vec<Value>> {
+        validate_page_size(self.page_size)?;
+        let mut next_cursor: Option<String> = None;
+        let mut all_results: Vec<Value> = vec![];
+        loop {
+            let request = self
+                .request(Method::GET, url)?
+                .query(&pagination_params(self.page_size, next_cursor.as_deref()));
+            next_cursor = collect_results(self.send(request)?, &mut all_results)?;
+            if next_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(all_results)
+    }
+
+    /// Build a request with headers common to all Notion API calls
+    fn request(&self, method: Method, url: &str) -> Result<RequestBuilder> {
+        let headers = request_headers(&self.api_key, &self.notion_version)?;
+        Ok(self.http.request(method, url).headers(headers))
+    }
+
+    /// Send the request and return the response JSON, or an error if Notion API returned an error object.
+    /// Requests which are rate limited or fail with a server error are sent again up to `max_retries` times.
+    fn send(&self, mut request: RequestBuilder) -> Result<Value> {
+        let mut retries = 0;
+        loop {
+            // Requests only have JSON bodies, so they can always be cloned
+            let retry = request.try_clone();
+            self.rate_limiter.acquire_blocking();
+            let response = request.send()?;
+            let status = response.status().as_u16();
+            let wait = next_retry_wait(
+                status,
+                response.headers(),
+                retries,
+                self.max_retries,
+                self.retry_backoff,
+            );
+            match (retry, wait) {
+                (Some(retry), Some(wait)) => {
+                    self.rate_limiter.record_retry();
+                    std::thread::sleep(wait);
+                    request = retry;
+                    retries += 1;
+                }
+                _ => {
+                    let body = response.text()?;
+                    return parse_response(status, &body);
+                }
+            }
+        }
+    }
+}
+
+/// How long to wait before sending the request again, or None if the response should be returned as it is.
+/// Shared by the blocking and the async clients so that both retry the same responses.
+pub(crate) fn next_retry_wait(
+    status: u16,
+    headers: &HeaderMap,
+    retries: u32,
+    max_retries: u32,
+    backoff: Duration,
+) -> Option<Duration> {
+    if !is_retryable(status) || retries >= max_retries {
+        return None;
+    }
+    let wait = retry_wait(headers, backoff, retries);
+    warn!(
+        "Notion API responded with {}, retrying in {:?} ({}/{})",
+        status,
+        wait,
+        retries + 1,
+        max_retries
+    );
+    Some(wait)
+}
+
+/// Whether the request may succeed if it's sent again: rate limited, or a server error such as 502 or 503
+fn is_retryable(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// How long to wait before the retry. Retry-After header in seconds is honored,
+/// otherwise the wait doubles from `backoff` on each retry.
+fn retry_wait(headers: &HeaderMap, backoff: Duration, retries: u32) -> Duration {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| backoff * 2u32.saturating_pow(retries))
+}
+
+/// Whether the API version models databases as a set of data sources.
+/// Notion-Version is a date like "2025-09-03", so it can be compared as a string.
+pub(crate) fn uses_data_sources(notion_version: &str) -> bool {
+    notion_version >= DATA_SOURCES_NOTION_VERSION
+}
+
+/// URL to query pages. If the schema belongs to a data source, pages are queried from the data source.
+pub(crate) fn query_url(
+    base_url: &str,
+    database_id: &str,
+    schema: &NotionDatabaseSchema,
+) -> String {
+    match &schema.data_source {
+        Some(data_source) => format!("{0}/data_sources/{1}/query", base_url, data_source.id),
+        None => format!("{0}/databases/{1}/query", base_url, database_id),
+    }
+}
+
+pub(crate) fn database_url(base_url: &str, database_id: &str) -> Result<String> {
+    let database_id = normalize_notion_id(database_id)?;
+    Ok(format!("{0}/databases/{1}", base_url, database_id))
+}
+
+pub(crate) fn data_source_url(base_url: &str, data_source_id: &str) -> Result<String> {
+    let data_source_id = normalize_notion_id(data_source_id)?;
+    Ok(format!("{0}/data_sources/{1}", base_url, data_source_id))
+}
+
+pub(crate) fn page_url(base_url: &str, page_id: &str) -> Result<String> {
+    let page_id = normalize_notion_id(page_id)?;
+    Ok(format!("{0}/pages/{1}", base_url, page_id))
+}
+
+pub(crate) fn block_children_url(base_url: &str, block_id: &str) -> Result<String> {
+    let block_id = normalize_notion_id(block_id)?;
+    Ok(format!("{0}/blocks/{1}/children", base_url, block_id))
+}
+
+/// URL of items of the page property. Property ids aren't Notion ids, so only the page id is normalized.
+pub(crate) fn property_items_url(
+    base_url: &str,
+    page_id: &str,
+    property_id: &str,
+) -> Result<String> {
+    let page_id = normalize_notion_id(page_id)?;
+    Ok(format!(
+        "{0}/pages/{1}/properties/{2}",
+        base_url, page_id, property_id
+    ))
+}
+
+/// Headers common to all Notion API calls.
+/// The authorization header is marked as sensitive so that it doesn't appear in debug output.
+pub(crate) fn request_headers(api_key: &str, notion_version: &str) -> Result<HeaderMap> {
+    let invalid = |name: &str| {
+        Error::InvalidArgument(format!("{} contains characters invalid in a header", name))
+    };
+    let mut authorization = HeaderValue::from_str(&format!("Bearer {}", api_key))
+        .map_err(|_| invalid("The API key"))?;
+    authorization.set_sensitive(true);
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, authorization);
+    headers.insert(
+        "Notion-Version",
+        HeaderValue::from_str(notion_version).map_err(|_| invalid("Notion-Version"))?,
+    );
+    Ok(headers)
+}
+
+/// Query string of a paginated GET endpoint
+pub(crate) fn pagination_params(
+    page_size: u32,
+    start_cursor: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let mut params = vec![("page_size", page_size.to_string())];
+    if let Some(cursor) = start_cursor {
+        params.push(("start_cursor", cursor.to_string()));
+    }
+    params
+}
+
+/// Add results of a response of a paginated GET endpoint, and return the cursor of the next request if there are more.
+/// A response which isn't a list, e.g. a single property item, is added as the only item.
+pub(crate) fn collect_results(resp: Value, all_results: &mut Vec<Value>) -> Result<Option<String>> {
+    if resp.get("object").and_then(|o| o.as_str()) != Some("list") {
+        all_results.push(resp);
+        return Ok(None);
+    }
+    let results = resp
+        .get("results")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| Error::parse("results", r#"It must have "results" array."#))?;
+    all_results.extend(results.iter().cloned());
+    get_next_cursor(&resp)
+}
+
+/// Whether the database request failed because the id isn't of a database, in which case it may be of a page
+pub(crate) fn is_not_found(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::NotionApi { status: 400, .. } | Error::NotionApi { status: 404, .. }
+    )
+}
+
+/// Error for a page id given where a database id is expected
+pub(crate) fn page_is_given_error(id: &str) -> Error {
+    Error::InvalidArgument(format!(
+        "{} is a page, not a database. Specify a database, whose id can be found with list-databases",
+        id
+    ))
+}
+
+/// Return an error unless Notion API accepts the page size
+pub(crate) fn validate_page_size(page_size: u32) -> Result<()> {
+    if (1..=MAX_PAGE_SIZE).contains(&page_size) {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!(
+            "Page size must be between 1 and {}, but was {}",
+            MAX_PAGE_SIZE, page_size
+        )))
+    }
+}
+
+/// Body of a query request, which adds pagination to the filter and sorts in `query`
+pub(crate) fn query_body(query: &Value, page_size: u32, start_cursor: Option<&str>) -> Value {
+    let mut query = query.clone();
+    query["page_size"] = page_size.into();
+    if let Some(cursor) = start_cursor {
+        query["start_cursor"] = cursor.into();
+    }
+    query
+}
+
+/// Parse the response body as JSON, and return an error if Notion API returned an error object
+pub(crate) fn parse_response(status: u16, body: &str) -> Result<Value> {
+    let resp = serde_json::from_str::<Value>(body).map_err(|_| Error::NotionApi {
+        status,
+        code: "".to_string(),
+        message: format!("Response is not JSON: {}", body),
+        request_id: None,
+    })?;
+    validate_response(status, &resp)?;
+    Ok(resp)
+}
+
+fn validate_response(status: u16, resp: &Value) -> Result<()> {
+    let json_keys = vec![JsonKey::String("object")];
+    let object_field = dig_json(resp, &json_keys)
+        .and_then(|o| o.as_str())
+        .ok_or_else(|| {
+            Error::parse(
+                "object",
+                &format!("Unexpected response from Notion API: {}", resp),
+            )
+        })?;
+
+    if object_field == "error" {
+        let field = |key: &str| {
+            resp.get(key)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string())
+        };
+        Err(Error::NotionApi {
+            status,
+            code: field("code").unwrap_or_default(),
+            message: field("message").unwrap_or_default(),
+            request_id: field("request_id"),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+impl PageSource for NotionClient {
+    fn get_database_schemas(&self, database_id: &str) -> Result<Vec<NotionDatabaseSchema>> {
+        NotionClient::get_database_schemas(self, database_id)
+    }
+
+    fn get_all_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        NotionClient::get_all_pages(self, database_id, schema, diagnostics)
+    }
+
+    fn get_pages(
+        &self,
+        database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        NotionClient::get_pages(self, database_id, schema, start_cursor, diagnostics)
+    }
+
+    fn query(&self, schema: &NotionDatabaseSchema) -> Result<Value> {
+        self.query.to_json(schema)
+    }
+
+    fn get_comments(&self, page_id: &str, include_blocks: bool) -> Result<Vec<NotionComment>> {
+        NotionClient::get_comments(self, page_id, include_blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uses_data_sources() {
+        let mut client = NotionClient::new("secret");
+        assert!(!client.uses_data_sources());
+
+        client.notion_version = "2025-09-03".into();
+        assert!(client.uses_data_sources());
+
+        client.notion_version = "2026-03-11".into();
+        assert!(client.uses_data_sources());
+    }
+
+    #[test]
+    fn test_retry_wait() {
+        assert!(is_retryable(429));
+        assert!(is_retryable(503));
+        assert!(!is_retryable(400));
+
+        let backoff = Duration::from_millis(500);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_wait(&headers, backoff, 0), backoff);
+        assert_eq!(retry_wait(&headers, backoff, 2), Duration::from_secs(2));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_wait(&headers, backoff, 2), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_query_body() {
+        let query = serde_json::json!({"sorts": []});
+        let body = query_body(&query, 100, None);
+        assert_eq!(body["page_size"], 100);
+        assert!(body.get("start_cursor").is_none());
+
+        let body = query_body(&query, 25, Some("cursor1"));
+        assert_eq!(body["page_size"], 25);
+        assert_eq!(body["start_cursor"], "cursor1");
+        assert_eq!(body["sorts"], query["sorts"]);
+
+        assert!(validate_page_size(1).is_ok());
+        assert!(validate_page_size(100).is_ok());
+        assert!(validate_page_size(0).is_err());
+        assert!(validate_page_size(101).is_err());
+    }
+
+    #[test]
+    fn test_validate_response() {
+        let resp = serde_json::json!({"object": "list", "results": []});
+        assert!(validate_response(200, &resp).is_ok());
+
+        let resp = serde_json::json!({
+            "object": "error",
+            "status": 401,
+            "code": "unauthorized",
+            "message": "API token is invalid.",
+            "request_id": "3b4c5d6e-0000-4000-8000-000000000000"
+        });
+        match validate_response(401, &resp) {
+            Err(Error::NotionApi {
+                status,
+                code,
+                message,
+                request_id,
+            }) => {
+                assert_eq!(status, 401);
+                assert_eq!(code, "unauthorized");
+                assert_eq!(message, "API token is invalid.");
+                assert_eq!(request_id.unwrap(), "3b4c5d6e-0000-4000-8000-000000000000");
+            }
+            result => panic!("Unexpected result: {:?}", result),
         }
     }
 }
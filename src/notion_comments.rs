@@ -0,0 +1,129 @@
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// A comment on a page or a block.
+/// See https://developers.notion.com/reference/comment-object
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotionComment {
+    pub id: String,
+    /// Page which the comment belongs to, even if it's commented on a block in the page
+    pub page_id: String,
+    /// Block which the comment is commented on. None if it's commented on the page itself.
+    pub block_id: Option<String>,
+    pub discussion_id: String,
+    /// Id of the user who wrote the comment
+    pub author_id: Option<String>,
+    pub created_time: String,
+    /// Rich text array as returned by Notion API
+    pub rich_text: Value,
+    pub plain_text: String,
+}
+
+/// Parse comments returned by `/v1/comments` for a block in the page
+pub fn parse_comments(results: &[Value], page_id: &str) -> Result<Vec<NotionComment>> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, comment)| parse_comment(comment, page_id, i))
+        .collect()
+}
+
+fn parse_comment(comment: &Value, page_id: &str, index: usize) -> Result<NotionComment> {
+    let string_field = |key: &str| {
+        comment
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+            .ok_or_else(|| {
+                Error::parse(
+                    &format!("results[{}].{}", index, key),
+                    &format!(r#"Comment must have "{}" string."#, key),
+                )
+            })
+    };
+    let rich_text = comment
+        .get("rich_text")
+        .filter(|rich_text| rich_text.is_array())
+        .cloned()
+        .ok_or_else(|| {
+            Error::parse(
+                &format!("results[{}].rich_text", index),
+                r#"Comment must have "rich_text" array."#,
+            )
+        })?;
+    let plain_text = rich_text
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|text| text.get("plain_text").and_then(|t| t.as_str()))
+        .collect::<String>();
+    let parent = comment.get("parent");
+    let block_id = match parent.and_then(|p| p.get("type")).and_then(|t| t.as_str()) {
+        Some("block_id") => parent
+            .and_then(|p| p.get("block_id"))
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string()),
+        _ => None,
+    };
+    let author_id = comment
+        .get("created_by")
+        .and_then(|user| user.get("id"))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string());
+
+    Ok(NotionComment {
+        id: string_field("id")?,
+        page_id: page_id.to_string(),
+        block_id,
+        discussion_id: string_field("discussion_id")?,
+        author_id,
+        created_time: string_field("created_time")?,
+        rich_text,
+        plain_text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_comments() {
+        let results = vec![
+            json!({
+                "object": "comment",
+                "id": "c1",
+                "parent": {"type": "page_id", "page_id": "p1"},
+                "discussion_id": "d1",
+                "created_time": "2022-07-15T16:52:00.000Z",
+                "last_edited_time": "2022-07-15T19:16:00.000Z",
+                "created_by": {"object": "user", "id": "u1"},
+                "rich_text": [
+                    {"type": "text", "plain_text": "Hello "},
+                    {"type": "mention", "plain_text": "@Alice"},
+                ],
+            }),
+            json!({
+                "object": "comment",
+                "id": "c2",
+                "parent": {"type": "block_id", "block_id": "b1"},
+                "discussion_id": "d2",
+                "created_time": "2022-07-16T00:00:00.000Z",
+                "created_by": {"object": "user", "id": "u2"},
+                "rich_text": [],
+            }),
+        ];
+        let comments = parse_comments(&results, "p1").unwrap();
+        assert_eq!(comments[0].plain_text, "Hello @Alice");
+        assert_eq!(comments[0].block_id, None);
+        assert_eq!(comments[0].author_id, Some("u1".to_string()));
+        assert_eq!(comments[1].page_id, "p1");
+        assert_eq!(comments[1].block_id, Some("b1".to_string()));
+        assert_eq!(comments[1].rich_text, json!([]));
+
+        let err = parse_comments(&[json!({"id": "c3"})], "p1").unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+    }
+}
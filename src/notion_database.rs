@@ -1,12 +1,17 @@
-use anyhow::{anyhow, Result};
+use crate::error::{Error, Result};
+use crate::json_util::dig_json;
+use crate::notion_pages::get_next_cursor;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Types of property values
 /// See https://developers.notion.com/reference/property-value-object
 /// > Possible values are "rich_text", "number", "select", "multi_select", "date",
 /// > "formula", "relation", "rollup", "title", "people", "files", "checkbox","url",
 /// > "email", "phone_number", "created_time", "created_by", "last_edited_time", and "last_edited_by".
+///
+/// Newer API versions added "status", "unique_id", "verification", "button" and "place".
 #[derive(Debug, PartialEq)]
 pub enum NotionPropertyType {
     RichText,
@@ -28,6 +33,11 @@ pub enum NotionPropertyType {
     CreatedBy,
     LastEditedTime,
     LastEditedBy,
+    Status,
+    UniqueId,
+    Verification,
+    Button,
+    Place,
     Other,
 }
 
@@ -36,21 +46,42 @@ pub struct NotionProperty {
     pub name: String,
     pub property_type: NotionPropertyType,
     pub property_raw_type: String,
+    /// Maps each option name of a status property to the name of its group.
+    /// Empty for other property types.
+    pub status_groups: HashMap<String, String>,
+}
+
+/// A data source of a database, introduced in Notion-Version 2025-09-03.
+/// A database contains one or more data sources, and each of them has its own properties and pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotionDataSource {
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Debug)]
 pub struct NotionDatabaseSchema {
+    /// Id of the database or the data source
+    pub id: String,
+    /// Plain text title of the database or the data source
+    pub title: String,
     pub properties: HashMap<String, NotionProperty>,
+    /// Data source which the schema belongs to.
+    /// None if the schema is fetched via the legacy database endpoint.
+    pub data_source: Option<NotionDataSource>,
+    /// Parent of the database, which is usually a page
+    pub parent: NotionParent,
 }
 
+/// Parse the schema from a database object (legacy API versions) or a data source object.
 pub fn parse_database_schema(database_resp: &Value) -> Result<NotionDatabaseSchema> {
-    validate_object_type(database_resp)?;
+    let object_field = validate_object_type(database_resp)?;
 
     let raw_properties = database_resp
         .as_object()
         .and_then(|resp| resp.get("properties"))
         .and_then(|prop| prop.as_object())
-        .ok_or_else(|| anyhow!(r#"It must have "properties" object."#))?;
+        .ok_or_else(|| Error::parse("properties", r#"It must have "properties" object."#))?;
 
     let properties = raw_properties
         .keys()
@@ -78,35 +109,267 @@ pub fn parse_database_schema(database_resp: &Value) -> Result<NotionDatabaseSche
                 "created_by" => NotionPropertyType::CreatedBy,
                 "last_edited_time" => NotionPropertyType::LastEditedTime,
                 "last_edited_by" => NotionPropertyType::LastEditedBy,
+                "status" => NotionPropertyType::Status,
+                "unique_id" => NotionPropertyType::UniqueId,
+                "verification" => NotionPropertyType::Verification,
+                "button" => NotionPropertyType::Button,
+                "place" => NotionPropertyType::Place,
                 _ => NotionPropertyType::Other,
             };
+            let status_groups = match property_type {
+                NotionPropertyType::Status => property
+                    .get("status")
+                    .map(parse_status_groups)
+                    .unwrap_or_default(),
+                _ => HashMap::new(),
+            };
             Some((
                 name.to_string(),
                 NotionProperty {
                     name: name.to_string(),
                     property_raw_type: property_raw_type.to_string(),
                     property_type,
+                    status_groups,
                 },
             ))
         })
         .collect::<HashMap<String, NotionProperty>>();
 
-    Ok(NotionDatabaseSchema { properties })
+    let id = database_resp
+        .get("id")
+        .and_then(|id| id.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let title = plain_text_title(database_resp);
+    let data_source = if object_field == "data_source" {
+        if id.is_empty() {
+            return Err(Error::parse("id", r#"It must have "id" string."#));
+        }
+        Some(NotionDataSource {
+            id: id.clone(),
+            name: title.clone(),
+        })
+    } else {
+        None
+    };
+
+    // A data source belongs to a database, and the parent of the database is given as "database_parent"
+    let parent_key = if data_source.is_some() {
+        "database_parent"
+    } else {
+        "parent"
+    };
+    let parent = database_resp
+        .get(parent_key)
+        .map(NotionParent::from_json)
+        .unwrap_or_default();
+
+    Ok(NotionDatabaseSchema {
+        id,
+        title,
+        properties,
+        data_source,
+        parent,
+    })
+}
+
+/// Parse the list of data sources from a database object like
+/// `{"object": "database", "data_sources": [{"id": "xxx", "name": "Tasks"}]}`
+pub fn parse_data_sources(database_resp: &Value) -> Result<Vec<NotionDataSource>> {
+    let object_field = validate_object_type(database_resp)?;
+    if object_field != "database" {
+        return Err(Error::parse(
+            "object",
+            &format!(
+                r#"It must have `"object": "database"`, but was "{}""#,
+                object_field
+            ),
+        ));
+    }
+
+    let data_sources = database_resp
+        .get("data_sources")
+        .and_then(|d| d.as_array())
+        .ok_or_else(|| Error::parse("data_sources", r#"It must have "data_sources" array."#))?
+        .iter()
+        .filter_map(|data_source| {
+            Some(NotionDataSource {
+                id: data_source.get("id")?.as_str()?.to_string(),
+                name: data_source
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect();
+    Ok(data_sources)
 }
 
-fn validate_object_type(database_resp: &Value) -> Result<()> {
+/// A database shared with the integration, found by `/search`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotionDatabaseSummary {
+    /// Id of the database. For a data source, id of the database which it belongs to.
+    pub id: String,
+    /// Plain text title
+    pub title: String,
+    /// Parent of the database like "page_id:xxxx", "block_id:xxxx" or "workspace"
+    pub parent: String,
+    pub last_edited_time: String,
+    pub url: Option<String>,
+}
+
+/// Parse databases and data sources in a search response.
+/// Returns them with the cursor of the next page.
+pub fn parse_database_search_results(
+    search_resp: &Value,
+) -> Result<(Vec<NotionDatabaseSummary>, Option<String>)> {
+    let results = search_resp
+        .get("results")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| Error::parse("results", r#"It must have "results" array."#))?;
+
+    let databases = results
+        .iter()
+        .filter_map(|result| {
+            let object = result.get("object")?.as_str()?;
+            let id = result.get("id")?.as_str()?;
+            let (id, parent) = match object {
+                "database" => (id, result.get("parent")),
+                // A data source belongs to a database, which is what can be exported
+                "data_source" => (
+                    dig_json(result, &["parent".into(), "database_id".into()])
+                        .and_then(|id| id.as_str())
+                        .unwrap_or(id),
+                    result.get("database_parent"),
+                ),
+                _ => return None,
+            };
+            Some(NotionDatabaseSummary {
+                id: id.to_string(),
+                title: plain_text_title(result),
+                parent: parent
+                    .map(|parent| NotionParent::from_json(parent).to_string())
+                    .unwrap_or_default(),
+                last_edited_time: result
+                    .get("last_edited_time")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                url: result
+                    .get("url")
+                    .and_then(|u| u.as_str())
+                    .map(|u| u.to_string()),
+            })
+        })
+        .collect();
+    let next_cursor = get_next_cursor(search_resp)?;
+    Ok((databases, next_cursor))
+}
+
+/// Parent of a database or a page.
+/// See https://developers.notion.com/reference/parent-object
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NotionParent {
+    /// Type like "page_id", "block_id", "database_id" or "workspace"
+    pub parent_type: String,
+    /// Id of the parent. None for the workspace.
+    pub id: Option<String>,
+}
+impl NotionParent {
+    /// Parse a parent object like `{"type": "page_id", "page_id": "xxxx"}`
+    pub fn from_json(parent: &Value) -> NotionParent {
+        let parent_type = parent
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default();
+        NotionParent {
+            parent_type: parent_type.to_string(),
+            id: parent
+                .get(parent_type)
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string()),
+        }
+    }
+}
+impl fmt::Display for NotionParent {
+    /// Format like "page_id:xxxx", or just "workspace"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.id {
+            Some(id) => write!(f, "{}:{}", self.parent_type, id),
+            None => write!(f, "{}", self.parent_type),
+        }
+    }
+}
+
+fn plain_text_title(resp: &Value) -> String {
+    resp.get("title")
+        .and_then(|t| t.as_array())
+        .map(|title| {
+            title
+                .iter()
+                .filter_map(|t| t.get("plain_text")?.as_str())
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+/// Resolve option name to group name from the status property configuration like
+/// `{"options": [{"id": "a", "name": "Done"}], "groups": [{"name": "Complete", "option_ids": ["a"]}]}`
+fn parse_status_groups(status: &Value) -> HashMap<String, String> {
+    let empty = vec![];
+    let options = status
+        .get("options")
+        .and_then(|o| o.as_array())
+        .unwrap_or(&empty);
+    let groups = status
+        .get("groups")
+        .and_then(|g| g.as_array())
+        .unwrap_or(&empty);
+
+    let mut group_by_option_id = HashMap::new();
+    for group in groups {
+        let group_name = match group.get("name").and_then(|n| n.as_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let option_ids = group
+            .get("option_ids")
+            .and_then(|ids| ids.as_array())
+            .unwrap_or(&empty);
+        for option_id in option_ids.iter().filter_map(|id| id.as_str()) {
+            group_by_option_id.insert(option_id, group_name);
+        }
+    }
+
+    options
+        .iter()
+        .filter_map(|option| {
+            let id = option.get("id")?.as_str()?;
+            let name = option.get("name")?.as_str()?;
+            let group_name = group_by_option_id.get(id)?;
+            Some((name.to_string(), group_name.to_string()))
+        })
+        .collect()
+}
+
+/// Returns the object type, which is either "database" or "data_source"
+fn validate_object_type(database_resp: &Value) -> Result<&str> {
     let object_field = database_resp
         .as_object()
         .and_then(|o| o.get("object"))
         .and_then(|o| o.as_str())
-        .ok_or_else(|| anyhow!(r#"It must have `"object": "database"`."#.to_string()))?;
+        .ok_or_else(|| Error::parse("object", r#"It must have `"object": "database"`."#))?;
 
-    if object_field == "database" {
-        Ok(())
+    if object_field == "database" || object_field == "data_source" {
+        Ok(object_field)
     } else {
-        Err(anyhow!(
-            r#"It must have `"object": "database"`, but was "{}""#,
-            object_field
+        Err(Error::parse(
+            "object",
+            &format!(
+                r#"It must have `"object": "database"` or `"object": "data_source"`, but was "{}""#,
+                object_field
+            ),
         ))
     }
 }
@@ -115,6 +378,49 @@ fn validate_object_type(database_resp: &Value) -> Result<()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_database_search_results() {
+        let data = serde_json::json!({
+            "object": "list",
+            "results": [
+                {
+                    "object": "database",
+                    "id": "8a281474-f071-4c54-8afc-17d8a4b7c782",
+                    "title": [{"plain_text": "Tasks"}],
+                    "parent": {"type": "page_id", "page_id": "5256af6e-80cc-4c63-a6f2-6fc9e4166239"},
+                    "last_edited_time": "2022-03-12T00:16:00.000Z",
+                    "url": "https://www.notion.so/8a281474f0714c548afc17d8a4b7c782"
+                },
+                {
+                    "object": "data_source",
+                    "id": "1f2e3d4c-0000-4000-8000-000000000001",
+                    "title": [{"plain_text": "Bugs"}],
+                    "parent": {"type": "database_id", "database_id": "0b7c1e4e-0000-4000-8000-000000000002"},
+                    "database_parent": {"type": "workspace", "workspace": true},
+                    "last_edited_time": "2025-09-03T00:00:00.000Z"
+                }
+            ],
+            "next_cursor": "cursor1",
+            "has_more": true
+        });
+        let (databases, next_cursor) = parse_database_search_results(&data).unwrap();
+        assert_eq!(next_cursor.unwrap(), "cursor1");
+        assert_eq!(
+            databases[0],
+            NotionDatabaseSummary {
+                id: "8a281474-f071-4c54-8afc-17d8a4b7c782".into(),
+                title: "Tasks".into(),
+                parent: "page_id:5256af6e-80cc-4c63-a6f2-6fc9e4166239".into(),
+                last_edited_time: "2022-03-12T00:16:00.000Z".into(),
+                url: Some("https://www.notion.so/8a281474f0714c548afc17d8a4b7c782".into()),
+            }
+        );
+        assert_eq!(databases[1].id, "0b7c1e4e-0000-4000-8000-000000000002");
+        assert_eq!(databases[1].title, "Bugs");
+        assert_eq!(databases[1].parent, "workspace");
+        assert_eq!(databases[1].url, None);
+    }
+
     #[test]
     fn test_validate_object_type() {
         let data = r#"
@@ -125,6 +431,14 @@ mod tests {
         let json = serde_json::from_str(data).unwrap();
         assert!(validate_object_type(&json).is_ok());
 
+        let data = r#"
+        {
+            "object": "data_source"
+        }
+        "#;
+        let json = serde_json::from_str(data).unwrap();
+        assert_eq!(validate_object_type(&json).unwrap(), "data_source");
+
         let data = r#"
         {
             "object": "xxx"
@@ -139,4 +453,42 @@ mod tests {
         let json = serde_json::from_str(data).unwrap();
         assert!(validate_object_type(&json).is_err());
     }
+
+    #[test]
+    fn test_parse_status_groups() {
+        let data = r#"
+        {
+            "options": [
+                {"id": "a", "name": "Not started"},
+                {"id": "b", "name": "Done"},
+                {"id": "c", "name": "Orphan"}
+            ],
+            "groups": [
+                {"name": "To-do", "option_ids": ["a"]},
+                {"name": "Complete", "option_ids": ["b"]}
+            ]
+        }
+        "#;
+        let json = serde_json::from_str(data).unwrap();
+        let groups = parse_status_groups(&json);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups.get("Not started").unwrap(), "To-do");
+        assert_eq!(groups.get("Done").unwrap(), "Complete");
+    }
+
+    #[test]
+    fn test_parse_status_property_without_config() {
+        let json = serde_json::json!({
+            "object": "database",
+            "id": "database1",
+            "title": [],
+            "properties": {
+                "Status": {"id": "a", "name": "Status", "type": "status"},
+            },
+        });
+        let schema = parse_database_schema(&json).unwrap();
+        let status = &schema.properties["Status"];
+        assert_eq!(status.property_type, NotionPropertyType::Status);
+        assert!(status.status_groups.is_empty());
+    }
 }
@@ -0,0 +1,119 @@
+use crate::error::{Error, Result};
+
+/// Whether the input is meant as an id or a URL of Notion rather than a title.
+/// Short numbers like "2024" are taken as titles.
+pub fn is_id_or_url(input: &str) -> bool {
+    let input = input.trim();
+    input.starts_with("https://")
+        || input.starts_with("http://")
+        || input.contains("notion.so/")
+        || input.contains("notion.site/")
+        || (input.replace('-', "").len() >= 16
+            && input.chars().all(|c| c.is_ascii_hexdigit() || c == '-'))
+}
+
+/// Normalize an id or a URL of Notion into a dashed id like "8a281474-f071-4c54-8afc-17d8a4b7c782".
+///
+/// Accepted forms are ids with or without dashes, and URLs like
+/// "https://www.notion.so/workspace/Tasks-8a281474f0714c548afc17d8a4b7c782?v=..."
+/// where the id is at the end of the path. Query strings such as the view id are ignored.
+pub fn normalize_notion_id(input: &str) -> Result<String> {
+    let invalid = |message: &str| {
+        Error::InvalidArgument(format!(
+            r#""{}" is not a valid Notion id: {}"#,
+            input, message
+        ))
+    };
+
+    let trimmed = input.trim();
+    let path = trimmed.split(['?', '#']).next().unwrap_or_default();
+    let last_segment = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    // Page titles come before the id in URLs like "Tasks-8a281474f0714c548afc17d8a4b7c782"
+    let candidate = if last_segment == trimmed || is_uuid_shape(last_segment) {
+        last_segment
+    } else {
+        match last_segment.rsplit('-').next() {
+            Some(id) if id.len() == 32 => id,
+            _ => return Err(invalid("no id is found in the URL")),
+        }
+    };
+
+    let hex = candidate.replace('-', "");
+    if hex.len() != 32 {
+        return Err(invalid("it must have 32 hexadecimal digits"));
+    }
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("it must consist of hexadecimal digits"));
+    }
+    let hex = hex.to_ascii_lowercase();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+/// Whether it's dashed like "8a281474-f071-4c54-8afc-17d8a4b7c782"
+fn is_uuid_shape(s: &str) -> bool {
+    let groups = s.split('-').map(|group| group.len()).collect::<Vec<_>>();
+    groups == [8, 4, 4, 4, 12] && s.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ID: &str = "8a281474-f071-4c54-8afc-17d8a4b7c782";
+
+    #[test]
+    fn test_normalize_notion_id() {
+        let inputs = [
+            "8a281474f0714c548afc17d8a4b7c782",
+            "8a281474-f071-4c54-8afc-17d8a4b7c782",
+            "8A281474F0714C548AFC17D8A4B7C782",
+            " 8a281474f0714c548afc17d8a4b7c782\n",
+            "https://www.notion.so/8a281474f0714c548afc17d8a4b7c782",
+            "https://www.notion.so/workspace/Tasks-8a281474f0714c548afc17d8a4b7c782",
+            "https://www.notion.so/workspace/My-tasks-8a281474f0714c548afc17d8a4b7c782?v=0f5d4e2a9b3c4d5e8f7a6b5c4d3e2f1a",
+            "https://www.notion.so/8a281474f0714c548afc17d8a4b7c782?v=0f5d4e2a9b3c4d5e8f7a6b5c4d3e2f1a&pvs=4",
+            "https://workspace.notion.site/Tasks-8a281474f0714c548afc17d8a4b7c782/",
+            "notion.so/8a281474-f071-4c54-8afc-17d8a4b7c782#section",
+        ];
+        for input in inputs {
+            assert_eq!(normalize_notion_id(input).unwrap(), ID, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_normalize_invalid_notion_id() {
+        let inputs = [
+            "8a281474f0714c548afc17d8a4b7c78",
+            "8a281474f0714c548afc17d8a4b7c7821",
+            "xa281474f0714c548afc17d8a4b7c782",
+            "https://www.notion.so/workspace/Tasks",
+            "",
+        ];
+        for input in inputs {
+            let err = normalize_notion_id(input).unwrap_err();
+            assert_eq!(err.exit_code(), 2, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_id_or_url() {
+        assert!(is_id_or_url("8a281474f0714c548afc17d8a4b7c782"));
+        assert!(is_id_or_url("8a281474-f071-4c54"));
+        assert!(is_id_or_url("https://www.notion.so/Tasks"));
+        assert!(!is_id_or_url("Tasks"));
+        assert!(!is_id_or_url("Bugs 2024"));
+        assert!(!is_id_or_url("2024"));
+        assert!(!is_id_or_url(""));
+    }
+}
@@ -1,16 +1,19 @@
+use crate::diagnostics::{ParseDiagnostic, ParseDiagnostics};
+use crate::error::{Error, Result};
 use crate::json_util::{dig_json, JsonKey};
-use crate::notion_database::{NotionDatabaseSchema, NotionPropertyType};
-use anyhow::{anyhow, Result};
+use crate::notion_database::{NotionDatabaseSchema, NotionProperty, NotionPropertyType};
 use rusqlite::ToSql;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NotionPropertyValue {
     Text(String),
     Number(f64),
     Json(Value),
     Boolean(bool),
+    /// Empty value such as a number set to null or an unselected select
+    Null,
 }
 impl ToSql for NotionPropertyValue {
     fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
@@ -21,6 +24,35 @@ impl ToSql for NotionPropertyValue {
                 serde_json::to_string(value).unwrap(),
             )),
             NotionPropertyValue::Boolean(value) => value.to_sql(),
+            NotionPropertyValue::Null => {
+                Ok(rusqlite::types::ToSqlOutput::from(rusqlite::types::Null))
+            }
+        }
+    }
+}
+
+impl NotionPropertyValue {
+    /// Typed JSON value, e.g. a number is a JSON number and a multi-select is an array
+    pub fn to_json(&self) -> Value {
+        match self {
+            NotionPropertyValue::Text(value) => Value::String(value.clone()),
+            NotionPropertyValue::Number(value) => serde_json::Number::from_f64(*value)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            NotionPropertyValue::Json(value) => value.clone(),
+            NotionPropertyValue::Boolean(value) => Value::Bool(*value),
+            NotionPropertyValue::Null => Value::Null,
+        }
+    }
+
+    /// Text of the value in a flat file like CSV. JSON values are serialized and Null is empty.
+    pub fn to_text(&self) -> String {
+        match self {
+            NotionPropertyValue::Text(value) => value.clone(),
+            NotionPropertyValue::Number(value) => value.to_string(),
+            NotionPropertyValue::Json(value) => serde_json::to_string(value).unwrap(),
+            NotionPropertyValue::Boolean(value) => value.to_string(),
+            NotionPropertyValue::Null => "".to_string(),
         }
     }
 }
@@ -43,6 +75,7 @@ struct NotionPageBuilder<'a> {
     schema: &'a NotionDatabaseSchema,
     TITLE_JSON_PATH: Vec<JsonKey<'a>>,
     SELECT_JSON_PATH: Vec<JsonKey<'a>>,
+    STATUS_JSON_PATH: Vec<JsonKey<'a>>,
 }
 impl NotionPageBuilder<'_> {
     fn new(schema: &NotionDatabaseSchema) -> NotionPageBuilder<'_> {
@@ -50,106 +83,256 @@ impl NotionPageBuilder<'_> {
             schema,
             TITLE_JSON_PATH: vec!["title".into(), 0.into(), "plain_text".into()],
             SELECT_JSON_PATH: vec!["select".into(), "name".into()],
+            STATUS_JSON_PATH: vec!["status".into(), "name".into()],
         }
     }
 
-    fn from(&self, json_entry: &Map<String, Value>) -> Option<NotionPage> {
-        let id = json_entry.get("id")?.as_str()?.to_string();
+    fn from(
+        &self,
+        json_entry: &Map<String, Value>,
+        json_path: &str,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Option<NotionPage> {
+        let page_id = json_entry
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string());
+        let mut page = match page_without_properties(json_entry) {
+            Ok(page) => page,
+            Err((key, reason)) => {
+                diagnostics.push(ParseDiagnostic {
+                    page_id,
+                    property_name: None,
+                    json_path: format!("{}.{}", json_path, key),
+                    reason: reason.to_string(),
+                });
+                return None;
+            }
+        };
 
-        let url = json_entry.get("url")?.as_str()?.to_string();
-        let created_time = json_entry.get("created_time")?.as_str()?.to_owned();
-        let created_by = json_entry.get("created_by")?.clone();
-        let last_edited_time = json_entry.get("last_edited_time")?.as_str()?.to_owned();
-        let last_edited_by = json_entry.get("last_edited_by")?.clone();
-        let archived = json_entry.get("archived")?.as_bool()?;
+        let properties_object = match json_entry.get("properties").and_then(|p| p.as_object()) {
+            Some(properties_object) => properties_object,
+            None => {
+                diagnostics.push(ParseDiagnostic {
+                    page_id,
+                    property_name: None,
+                    json_path: format!("{}.properties", json_path),
+                    reason: "properties object is missing".to_string(),
+                });
+                return None;
+            }
+        };
+        for (key, property) in properties_object {
+            let property_path = format!("{}.properties[{}]", json_path, Value::from(key.as_str()));
+            let property_schema = match self.schema.properties.get(key) {
+                Some(property_schema) => property_schema,
+                None => {
+                    diagnostics.push(ParseDiagnostic {
+                        page_id: page_id.clone(),
+                        property_name: Some(key.to_string()),
+                        json_path: property_path,
+                        reason: "property is not in the database schema".to_string(),
+                    });
+                    continue;
+                }
+            };
+            match self.property_value(property_schema, property) {
+                Some(value) => {
+                    page.properties.insert(key.to_string(), value);
+                }
+                None => {
+                    let raw_type = &property_schema.property_raw_type;
+                    diagnostics.push(ParseDiagnostic {
+                        page_id: page_id.clone(),
+                        property_name: Some(key.to_string()),
+                        json_path: format!("{}.{}", property_path, raw_type),
+                        reason: describe_invalid_value(property.get(raw_type)).to_string(),
+                    });
+                }
+            }
+        }
 
-        let properties_object = json_entry.get("properties")?.as_object()?;
-        let properties = properties_object
-            .iter()
-            .filter_map(|(key, property)| {
-                let property_schema = self.schema.properties.get(key)?;
-                let value: NotionPropertyValue = match property_schema.property_type {
-                    // TODO: convert to plain text
-                    NotionPropertyType::RichText => {
-                        NotionPropertyValue::Json(property.get("rich_text")?.clone())
-                    }
-                    NotionPropertyType::Number => {
-                        NotionPropertyValue::Number(property.get("number")?.as_f64()?)
-                    }
-                    NotionPropertyType::Select => NotionPropertyValue::Text(
-                        dig_json(property, &self.SELECT_JSON_PATH)?
-                            .as_str()?
-                            .to_string(),
-                    ),
-                    NotionPropertyType::Title => NotionPropertyValue::Text(
-                        dig_json(property, &self.TITLE_JSON_PATH)?
-                            .as_str()?
-                            .to_string(),
-                    ),
-                    NotionPropertyType::Checkbox => {
-                        NotionPropertyValue::Boolean(property.get("checkbox")?.as_bool()?)
-                    }
-                    NotionPropertyType::Url => {
-                        NotionPropertyValue::Text(property.get("url")?.as_str()?.to_string())
-                    }
-                    NotionPropertyType::Email => {
-                        NotionPropertyValue::Text(property.get("email")?.as_str()?.to_string())
-                    }
-                    NotionPropertyType::PhoneNumber => NotionPropertyValue::Text(
-                        property.get("phone_number")?.as_str()?.to_string(),
-                    ),
-                    NotionPropertyType::CreatedTime => NotionPropertyValue::Text(
-                        property.get("created_time")?.as_str()?.to_string(),
-                    ),
-                    NotionPropertyType::LastEditedTime => NotionPropertyValue::Text(
-                        property.get("last_edited_time")?.as_str()?.to_string(),
-                    ),
-                    NotionPropertyType::Other => NotionPropertyValue::Json(property.clone()),
-                    _ => NotionPropertyValue::Json(
-                        property.get(&property_schema.property_raw_type)?.clone(),
-                    ),
-                };
-                Some((key.to_string(), value))
-            })
-            .collect::<HashMap<String, NotionPropertyValue>>();
+        Some(page)
+    }
+
+    fn property_value(
+        &self,
+        property_schema: &NotionProperty,
+        property: &Value,
+    ) -> Option<NotionPropertyValue> {
+        if is_empty_value(property_schema, property) {
+            return Some(NotionPropertyValue::Null);
+        }
+
+        let value = match property_schema.property_type {
+            // TODO: convert to plain text
+            NotionPropertyType::RichText => {
+                NotionPropertyValue::Json(property.get("rich_text")?.clone())
+            }
+            NotionPropertyType::Number => {
+                NotionPropertyValue::Number(property.get("number")?.as_f64()?)
+            }
+            NotionPropertyType::Select => NotionPropertyValue::Text(
+                dig_json(property, &self.SELECT_JSON_PATH)?
+                    .as_str()?
+                    .to_string(),
+            ),
+            NotionPropertyType::Title => NotionPropertyValue::Text(
+                dig_json(property, &self.TITLE_JSON_PATH)?
+                    .as_str()?
+                    .to_string(),
+            ),
+            NotionPropertyType::Checkbox => {
+                NotionPropertyValue::Boolean(property.get("checkbox")?.as_bool()?)
+            }
+            NotionPropertyType::Url => {
+                NotionPropertyValue::Text(property.get("url")?.as_str()?.to_string())
+            }
+            NotionPropertyType::Email => {
+                NotionPropertyValue::Text(property.get("email")?.as_str()?.to_string())
+            }
+            NotionPropertyType::PhoneNumber => {
+                NotionPropertyValue::Text(property.get("phone_number")?.as_str()?.to_string())
+            }
+            NotionPropertyType::CreatedTime => {
+                NotionPropertyValue::Text(property.get("created_time")?.as_str()?.to_string())
+            }
+            NotionPropertyType::LastEditedTime => {
+                NotionPropertyValue::Text(property.get("last_edited_time")?.as_str()?.to_string())
+            }
+            NotionPropertyType::Status => NotionPropertyValue::Text(
+                dig_json(property, &self.STATUS_JSON_PATH)?
+                    .as_str()?
+                    .to_string(),
+            ),
+            NotionPropertyType::UniqueId => {
+                NotionPropertyValue::Text(format_unique_id(property.get("unique_id")?)?)
+            }
+            NotionPropertyType::Verification => {
+                NotionPropertyValue::Json(property.get("verification")?.clone())
+            }
+            NotionPropertyType::Button => {
+                NotionPropertyValue::Json(property.get("button")?.clone())
+            }
+            NotionPropertyType::Place => NotionPropertyValue::Json(property.get("place")?.clone()),
+            NotionPropertyType::Other => NotionPropertyValue::Json(property.clone()),
+            _ => {
+                NotionPropertyValue::Json(property.get(&property_schema.property_raw_type)?.clone())
+            }
+        };
+        Some(value)
+    }
+}
 
-        Some(NotionPage {
-            id,
-            properties,
-            url,
-            created_time,
-            created_by,
-            last_edited_by,
-            last_edited_time,
-            archived,
-        })
+/// Whether the property has no value, e.g. `{"type": "number", "number": null}` or an empty title.
+/// Properties of type "other" keep the whole JSON, so they are never empty.
+fn is_empty_value(property_schema: &NotionProperty, property: &Value) -> bool {
+    if property_schema.property_type == NotionPropertyType::Other {
+        return false;
+    }
+    match property.get(&property_schema.property_raw_type) {
+        Some(Value::Null) => true,
+        Some(Value::Array(title)) if property_schema.property_type == NotionPropertyType::Title => {
+            title.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Read page fields other than properties.
+/// Returns the key and the reason if a required field can't be read.
+fn page_without_properties(
+    json_entry: &Map<String, Value>,
+) -> std::result::Result<NotionPage, (&'static str, &'static str)> {
+    let required = |key: &'static str| match json_entry.get(key) {
+        None => Err((key, "field is missing")),
+        Some(Value::Null) => Err((key, "value is null")),
+        Some(value) => Ok(value),
+    };
+    let required_str = |key: &'static str| {
+        required(key)?
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or((key, "value is not a string"))
+    };
+
+    Ok(NotionPage {
+        id: required_str("id")?,
+        properties: HashMap::new(),
+        url: required_str("url")?,
+        created_time: required_str("created_time")?,
+        created_by: required("created_by")?.clone(),
+        last_edited_time: required_str("last_edited_time")?,
+        last_edited_by: required("last_edited_by")?.clone(),
+        archived: required("archived")?
+            .as_bool()
+            .ok_or(("archived", "value is not a boolean"))?,
+    })
+}
+
+/// Explain why a property value couldn't be converted
+fn describe_invalid_value(value: Option<&Value>) -> &'static str {
+    match value {
+        None => "field is missing",
+        Some(Value::Null) => "value is null",
+        Some(Value::Array(array)) if array.is_empty() => "value is empty",
+        Some(_) => "value has unexpected shape",
+    }
+}
+
+/// Format unique id value like `{"prefix": "TASK", "number": 42}` into "TASK-42",
+/// which is how Notion displays it. The prefix is optional.
+fn format_unique_id(unique_id: &Value) -> Option<String> {
+    let number = unique_id.get("number")?.as_i64()?;
+    match unique_id.get("prefix").and_then(|p| p.as_str()) {
+        Some(prefix) => Some(format!("{}-{}", prefix, number)),
+        None => Some(number.to_string()),
     }
 }
 
+/// Parse pages in a query response. Skipped pages and properties are only logged.
 pub fn parse_notion_page_list(
     schema: &NotionDatabaseSchema,
     query_resp: &Value,
+) -> Result<(Vec<NotionPage>, Option<String>)> {
+    parse_notion_page_list_with_diagnostics(schema, query_resp, &mut ParseDiagnostics::new())
+}
+
+/// Parse pages in a query response, recording skipped pages and properties into `diagnostics`
+pub fn parse_notion_page_list_with_diagnostics(
+    schema: &NotionDatabaseSchema,
+    query_resp: &Value,
+    diagnostics: &mut ParseDiagnostics,
 ) -> Result<(Vec<NotionPage>, Option<String>)> {
     validate_object_type(query_resp)?;
 
-    let next_cursor = get_next_cursor(query_resp);
+    let next_cursor = get_next_cursor(query_resp)?;
 
     let results_json_keys = vec![JsonKey::String("results")];
     let results = dig_json(query_resp, &results_json_keys)
         .and_then(|results| results.as_array())
-        .map(|results| {
-            results
-                .iter()
-                .filter_map(|r| r.as_object())
-                .collect::<Vec<_>>()
-        })
-        .ok_or_else(|| anyhow!(r#"It must have "results" as arrray of objects."#))?;
+        .ok_or_else(|| {
+            Error::parse("results", r#"It must have "results" as arrray of objects."#)
+        })?;
 
     let page_builder = NotionPageBuilder::new(schema);
-    let pages: Vec<NotionPage> = results
-        .iter()
-        .filter_map(|&result| page_builder.from(result))
-        .collect::<Vec<_>>();
+    let mut pages: Vec<NotionPage> = vec![];
+    for (index, result) in results.iter().enumerate() {
+        let json_path = format!("results[{}]", index);
+        match result.as_object() {
+            Some(result) => {
+                if let Some(page) = page_builder.from(result, &json_path, diagnostics) {
+                    pages.push(page);
+                }
+            }
+            None => diagnostics.push(ParseDiagnostic {
+                page_id: None,
+                property_name: None,
+                json_path,
+                reason: "result is not an object".to_string(),
+            }),
+        }
+    }
 
     Ok((pages, next_cursor))
 }
@@ -158,21 +341,150 @@ fn validate_object_type(query_resp: &Value) -> Result<()> {
     let json_keys = vec![JsonKey::String("object")];
     let object_field = dig_json(query_resp, &json_keys)
         .and_then(|o| o.as_str())
-        .ok_or_else(|| anyhow!(r#"It must have `"object": "list"`."#.to_string()))?;
+        .ok_or_else(|| Error::parse("object", r#"It must have `"object": "list"`."#))?;
 
     if object_field == "list" {
         Ok(())
     } else {
-        Err(anyhow!(
-            r#"It must have `"object": "list"`, but was "{}""#,
-            object_field
+        Err(Error::parse(
+            "object",
+            &format!(
+                r#"It must have `"object": "list"`, but was "{}""#,
+                object_field
+            ),
         ))
     }
 }
 
-fn get_next_cursor(query_resp: &Value) -> Option<String> {
+/// Cursor of the next page, or None if this is the last page.
+/// `has_more` takes precedence over `next_cursor` when the response has it.
+pub(crate) fn get_next_cursor(query_resp: &Value) -> Result<Option<String>> {
     let json_keys: Vec<JsonKey> = vec!["next_cursor".into()];
-    Some(dig_json(query_resp, &json_keys)?.as_str()?.to_string())
+    let next_cursor = dig_json(query_resp, &json_keys)
+        .and_then(|c| c.as_str())
+        .map(|c| c.to_string());
+    match query_resp.get("has_more").and_then(|h| h.as_bool()) {
+        Some(false) => Ok(None),
+        Some(true) => next_cursor.map(Some).ok_or_else(|| {
+            Error::parse(
+                "next_cursor",
+                r#"It must have "next_cursor" when "has_more" is true."#,
+            )
+        }),
+        None => Ok(next_cursor),
+    }
+}
+
+/// Maximum number of items in a property value returned by the query endpoint.
+/// Values with more items are truncated, and the rest must be fetched from the page property endpoint.
+pub static PROPERTY_ITEMS_LIMIT: usize = 25;
+
+/// A property value in a query response which may be truncated
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TruncatedProperty {
+    /// Index of the page in "results" of the query response
+    pub result_index: usize,
+    pub page_id: String,
+    pub property_name: String,
+    pub property_id: String,
+}
+
+/// Find property values which the query endpoint has truncated, which report `"has_more": true`.
+/// A value with exactly as many items as the limit isn't taken as truncated unless it reports so.
+pub(crate) fn find_truncated_properties(query_resp: &Value) -> Vec<TruncatedProperty> {
+    let results = match query_resp.get("results").and_then(|r| r.as_array()) {
+        Some(results) => results,
+        None => return vec![],
+    };
+
+    let mut truncated = vec![];
+    for (result_index, result) in results.iter().enumerate() {
+        let page_id = match result.get("id").and_then(|id| id.as_str()) {
+            Some(page_id) => page_id,
+            None => continue,
+        };
+        let properties = match result.get("properties").and_then(|p| p.as_object()) {
+            Some(properties) => properties,
+            None => continue,
+        };
+        for (property_name, property) in properties {
+            let property_id = match property.get("id").and_then(|id| id.as_str()) {
+                Some(property_id) => property_id,
+                None => continue,
+            };
+            if is_truncated_value(property) {
+                truncated.push(TruncatedProperty {
+                    result_index,
+                    page_id: page_id.to_string(),
+                    property_name: property_name.to_string(),
+                    property_id: property_id.to_string(),
+                });
+            }
+        }
+    }
+    truncated
+}
+
+fn is_truncated_value(property: &Value) -> bool {
+    let items = match property.get("type").and_then(|t| t.as_str()) {
+        Some(raw_type @ ("title" | "rich_text" | "people" | "relation")) => property.get(raw_type),
+        Some("rollup") => dig_json(property, &["rollup".into(), "array".into()]),
+        _ => return false,
+    };
+    let has_more = property.get("has_more").and_then(|h| h.as_bool());
+    items.and_then(|items| items.as_array()).is_some() && has_more == Some(true)
+}
+
+/// Replace the truncated value in the query response with all items
+/// fetched from `/pages/{page_id}/properties/{property_id}`.
+/// Each item is a property item object like `{"object": "property_item", "type": "relation", "relation": {"id": "..."}}`.
+pub(crate) fn complete_property_value(
+    query_resp: &mut Value,
+    truncated: &TruncatedProperty,
+    items: &[Value],
+) {
+    let property = match query_resp
+        .get_mut("results")
+        .and_then(|results| results.get_mut(truncated.result_index))
+        .and_then(|result| result.get_mut("properties"))
+        .and_then(|properties| properties.get_mut(&truncated.property_name))
+    {
+        Some(property) => property,
+        None => return,
+    };
+    let raw_type = match property.get("type").and_then(|t| t.as_str()) {
+        Some(raw_type) => raw_type.to_string(),
+        None => return,
+    };
+
+    if raw_type == "rollup" {
+        // Items of a rollup are property items of the rolled up type. Strip fields specific to property items
+        // so that they look like elements of the rollup array in the query response.
+        let array = items
+            .iter()
+            .map(|item| {
+                let mut item = item.clone();
+                if let Some(item) = item.as_object_mut() {
+                    item.remove("object");
+                    item.remove("id");
+                    item.remove("next_url");
+                }
+                item
+            })
+            .collect::<Vec<_>>();
+        if let Some(rollup) = property.get_mut("rollup") {
+            rollup["array"] = Value::Array(array);
+        }
+    } else {
+        let values = items
+            .iter()
+            .filter_map(|item| item.get(&raw_type).cloned())
+            .collect::<Vec<_>>();
+        property[&raw_type] = Value::Array(values);
+    }
+    if property.get("has_more").is_some() {
+        property["has_more"] = false.into();
+    }
 }
 
 #[cfg(test)]
@@ -203,4 +515,78 @@ mod tests {
         let json = serde_json::from_str(data).unwrap();
         assert!(validate_object_type(&json).is_err());
     }
+
+    #[test]
+    fn test_describe_invalid_value() {
+        assert_eq!(describe_invalid_value(None), "field is missing");
+        assert_eq!(describe_invalid_value(Some(&Value::Null)), "value is null");
+        assert_eq!(
+            describe_invalid_value(Some(&serde_json::json!([]))),
+            "value is empty"
+        );
+        assert_eq!(
+            describe_invalid_value(Some(&serde_json::json!("10"))),
+            "value has unexpected shape"
+        );
+    }
+
+    #[test]
+    fn test_complete_truncated_property() {
+        let relations = (0..PROPERTY_ITEMS_LIMIT)
+            .map(|i| serde_json::json!({ "id": format!("rel{}", i) }))
+            .collect::<Vec<_>>();
+        let mut query_resp = serde_json::json!({
+            "object": "list",
+            "results": [{
+                "id": "page1",
+                "properties": {
+                    "Related": {"id": "abc", "type": "relation", "relation": relations, "has_more": true},
+                    "Full": {"id": "jkl", "type": "relation", "relation": relations, "has_more": false},
+                    "People": {"id": "mno", "type": "people", "people": relations},
+                    "Tags": {"id": "def", "type": "multi_select", "multi_select": []},
+                    "Text": {"id": "ghi", "type": "rich_text", "rich_text": [{"plain_text": "a"}]}
+                }
+            }],
+            "next_cursor": null
+        });
+
+        let truncated = find_truncated_properties(&query_resp);
+        assert_eq!(
+            truncated,
+            vec![TruncatedProperty {
+                result_index: 0,
+                page_id: "page1".into(),
+                property_name: "Related".into(),
+                property_id: "abc".into(),
+            }]
+        );
+
+        let items = (0..30)
+            .map(|i| {
+                serde_json::json!({
+                    "object": "property_item",
+                    "id": "abc",
+                    "type": "relation",
+                    "relation": { "id": format!("rel{}", i) }
+                })
+            })
+            .collect::<Vec<_>>();
+        complete_property_value(&mut query_resp, &truncated[0], &items);
+        let related = &query_resp["results"][0]["properties"]["Related"];
+        assert_eq!(related["relation"].as_array().unwrap().len(), 30);
+        assert_eq!(related["relation"][29], serde_json::json!({"id": "rel29"}));
+        assert_eq!(related["has_more"], false);
+    }
+
+    #[test]
+    fn test_format_unique_id() {
+        let json = serde_json::from_str(r#"{"prefix": "TASK", "number": 42}"#).unwrap();
+        assert_eq!(format_unique_id(&json).unwrap(), "TASK-42");
+
+        let json = serde_json::from_str(r#"{"prefix": null, "number": 7}"#).unwrap();
+        assert_eq!(format_unique_id(&json).unwrap(), "7");
+
+        let json = serde_json::from_str(r#"{"prefix": "TASK", "number": null}"#).unwrap();
+        assert!(format_unique_id(&json).is_none());
+    }
 }
@@ -0,0 +1,335 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::exporter::{DatabaseRecord, Sink};
+use crate::notion_comments::NotionComment;
+use crate::notion_database::{NotionDatabaseSchema, NotionPropertyType};
+use crate::notion_pages::{NotionPage, NotionPropertyValue};
+use crate::sqlite::{
+    comment_row, database_row, page_metadata_row, ColumnNames, COMMENTS_COLUMNS, COMMENTS_TABLE,
+    DATABASES_COLUMNS, DATABASES_TABLE, PAGE_ID_COLUMN, PAGE_METADATA_COLUMNS, PAGE_METADATA_TABLE,
+};
+
+/// Timestamps are stored in milliseconds in UTC, which is the precision of Notion API
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+fn string_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true)))
+}
+
+/// Arrow schema of the page properties table for the Notion schema.
+/// Columns are the page id followed by the properties in the order of `ColumnNames`.
+///
+/// - number → Float64
+/// - checkbox → Boolean
+/// - date, created_time and last_edited_time → Timestamp. Only the start of a date range is kept.
+/// - multi_select → List<Utf8> of option names
+/// - others → Utf8, where values which aren't text are serialized as JSON
+pub fn arrow_schema(schema: &NotionDatabaseSchema) -> Schema {
+    let column_names = ColumnNames::new(schema);
+    let mut fields = vec![Field::new(PAGE_ID_COLUMN, DataType::Utf8, false)];
+    for name in column_names.property_names() {
+        let data_type = match schema.properties[name].property_type {
+            NotionPropertyType::Number => DataType::Float64,
+            NotionPropertyType::Checkbox => DataType::Boolean,
+            NotionPropertyType::Date
+            | NotionPropertyType::CreatedTime
+            | NotionPropertyType::LastEditedTime => timestamp_type(),
+            NotionPropertyType::MultiSelect => string_list_type(),
+            _ => DataType::Utf8,
+        };
+        fields.push(Field::new(name, data_type, true));
+    }
+    Schema::new(fields)
+}
+
+/// Sink which writes each table into a Parquet file in a directory, like "pages.parquet".
+/// The tables are the same as those of `SqliteSink`, and each call of `write_pages()` is written as a row group.
+pub struct ParquetSink {
+    pub directory: PathBuf,
+    /// Writers of page properties tables which are open until `finish()`
+    writers: HashMap<String, ArrowWriter<File>>,
+    /// Rows of the page metadata table by page id, which is shared among all page properties tables
+    page_metadata: BTreeMap<String, Vec<NotionPropertyValue>>,
+}
+impl ParquetSink {
+    pub fn new(directory: &str) -> ParquetSink {
+        ParquetSink {
+            directory: PathBuf::from(directory),
+            writers: HashMap::new(),
+            page_metadata: BTreeMap::new(),
+        }
+    }
+
+    fn create_writer(&self, table_name: &str, schema: SchemaRef) -> Result<ArrowWriter<File>> {
+        fs::create_dir_all(&self.directory).map_err(|err| self.output_error(&err))?;
+        let path = self.directory.join(format!("{}.parquet", table_name));
+        let file = File::create(&path).map_err(|err| self.output_error(&err))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        ArrowWriter::try_new(file, schema, Some(properties)).map_err(|err| self.output_error(&err))
+    }
+
+    /// Write the whole table at once
+    fn write_table<V: Borrow<NotionPropertyValue>>(
+        &self,
+        table_name: &str,
+        schema: Schema,
+        rows: &[Vec<V>],
+    ) -> Result<()> {
+        let schema = Arc::new(schema);
+        let batch = record_batch(schema.clone(), rows).map_err(|err| self.output_error(&err))?;
+        let mut writer = self.create_writer(table_name, schema)?;
+        writer
+            .write(&batch)
+            .and_then(|_| writer.close().map(|_| ()))
+            .map_err(|err| self.output_error(&err))
+    }
+
+    fn output_error(&self, err: &dyn std::error::Error) -> Error {
+        Error::Output {
+            path: self.directory.display().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Sink for ParquetSink {
+    fn create_table(&mut self, schema: &NotionDatabaseSchema, table_name: &str) -> Result<()> {
+        let writer = self.create_writer(table_name, Arc::new(arrow_schema(schema)))?;
+        // A writer left from the previous run is replaced, which overwrites the file
+        self.writers.insert(table_name.to_string(), writer);
+        Ok(())
+    }
+
+    fn write_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()> {
+        let column_names = ColumnNames::new(schema);
+        let rows = pages
+            .iter()
+            .map(|page| column_names.row(page))
+            .collect::<Vec<_>>();
+        let batch = record_batch(Arc::new(arrow_schema(schema)), &rows)
+            .map_err(|err| self.output_error(&err))?;
+        let writer = self.writers.get_mut(table_name).ok_or_else(|| {
+            Error::InvalidArgument(format!("Table {} is not created", table_name))
+        })?;
+        let result = writer.write(&batch).and_then(|_| writer.flush());
+        result.map_err(|err| self.output_error(&err))?;
+
+        for page in pages {
+            self.page_metadata
+                .insert(page.id.clone(), page_metadata_row(page));
+        }
+        Ok(())
+    }
+
+    fn write_databases(&mut self, databases: &[DatabaseRecord]) -> Result<()> {
+        let schema = fields_schema(
+            &DATABASES_COLUMNS,
+            [
+                (DataType::Utf8, false),
+                (DataType::Utf8, false),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+            ],
+        );
+        let rows = databases.iter().map(database_row).collect::<Vec<_>>();
+        self.write_table(DATABASES_TABLE, schema, &rows)
+    }
+
+    fn write_comments(&mut self, comments: &[NotionComment]) -> Result<()> {
+        let schema = fields_schema(
+            &COMMENTS_COLUMNS,
+            [
+                (DataType::Utf8, false),
+                (DataType::Utf8, false),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+                (timestamp_type(), true),
+                (DataType::Utf8, true),
+                (DataType::Utf8, true),
+            ],
+        );
+        let rows = comments.iter().map(comment_row).collect::<Vec<_>>();
+        self.write_table(COMMENTS_TABLE, schema, &rows)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for (_, writer) in self.writers.drain() {
+            writer.close().map_err(|err| Error::Output {
+                path: self.directory.display().to_string(),
+                message: err.to_string(),
+            })?;
+        }
+
+        let schema = fields_schema(
+            &PAGE_METADATA_COLUMNS,
+            [
+                (DataType::Utf8, false),
+                (DataType::Utf8, true),
+                (timestamp_type(), true),
+                (DataType::Utf8, true),
+                (timestamp_type(), true),
+                (DataType::Utf8, true),
+                (DataType::Boolean, true),
+            ],
+        );
+        let rows = self.page_metadata.values().collect::<Vec<_>>();
+        let rows = rows
+            .iter()
+            .map(|row| row.iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        self.write_table(PAGE_METADATA_TABLE, schema, &rows)
+    }
+}
+
+/// Schema of a table shared by all sinks, with the data type and the nullability of each column
+fn fields_schema<const N: usize>(columns: &[&str; N], types: [(DataType, bool); N]) -> Schema {
+    let fields = columns
+        .iter()
+        .zip(types)
+        .map(|(column, (data_type, nullable))| Field::new(*column, data_type, nullable))
+        .collect::<Vec<_>>();
+    Schema::new(fields)
+}
+
+/// Build a record batch from rows whose values are in the order of the fields
+fn record_batch<V: Borrow<NotionPropertyValue>>(
+    schema: SchemaRef,
+    rows: &[Vec<V>],
+) -> std::result::Result<RecordBatch, arrow_schema::ArrowError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let values = rows.iter().map(|row| row[i].borrow());
+            array(field.data_type(), values)
+        })
+        .collect::<Vec<_>>();
+    RecordBatch::try_new(schema, columns)
+}
+
+/// Convert values into an array of the type. Values which can't be converted are null.
+fn array<'a>(
+    data_type: &DataType,
+    values: impl Iterator<Item = &'a NotionPropertyValue>,
+) -> ArrayRef {
+    match data_type {
+        DataType::Float64 => Arc::new(
+            values
+                .map(|value| match value {
+                    NotionPropertyValue::Number(number) => Some(*number),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        DataType::Boolean => Arc::new(
+            values
+                .map(|value| match value {
+                    NotionPropertyValue::Boolean(boolean) => Some(*boolean),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Timestamp(_, _) => Arc::new(
+            values
+                .map(timestamp_millis)
+                .collect::<TimestampMillisecondArray>()
+                .with_timezone("UTC"),
+        ),
+        DataType::List(_) => {
+            let mut builder = ListBuilder::new(StringBuilder::new());
+            for value in values {
+                match value {
+                    NotionPropertyValue::Json(Value::Array(options)) => {
+                        for option in options {
+                            let name = option.get("name").and_then(|name| name.as_str());
+                            builder.values().append_option(name);
+                        }
+                        builder.append(true);
+                    }
+                    _ => builder.append(false),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        _ => Arc::new(
+            values
+                .map(|value| match value {
+                    NotionPropertyValue::Null => None,
+                    value => Some(value.to_text()),
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Milliseconds since the epoch of a timestamp like "2022-03-12T00:15:00.000Z",
+/// a date like "2022-03-12" or a date property like `{"start": "2022-03-12", "end": null}`
+fn timestamp_millis(value: &NotionPropertyValue) -> Option<i64> {
+    let text = match value {
+        NotionPropertyValue::Text(text) => text.as_str(),
+        NotionPropertyValue::Json(date) => date.get("start")?.as_str()?,
+        _ => return None,
+    };
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Some(datetime.timestamp_millis());
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_timestamp_millis() {
+        let text = |s: &str| NotionPropertyValue::Text(s.to_string());
+        assert_eq!(
+            timestamp_millis(&text("2022-03-12T00:15:00.000Z")),
+            Some(1647044100000)
+        );
+        assert_eq!(
+            timestamp_millis(&text("2022-03-12T09:15:00.000+09:00")),
+            Some(1647044100000)
+        );
+        assert_eq!(timestamp_millis(&text("2022-03-12")), Some(1647043200000));
+        assert_eq!(
+            timestamp_millis(&NotionPropertyValue::Json(
+                json!({"start": "2022-03-12", "end": "2022-03-13", "time_zone": null})
+            )),
+            Some(1647043200000)
+        );
+        assert_eq!(timestamp_millis(&text("yesterday")), None);
+        assert_eq!(timestamp_millis(&NotionPropertyValue::Null), None);
+    }
+}
@@ -0,0 +1,450 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+use crate::notion_database::{NotionDatabaseSchema, NotionPropertyType};
+
+/// Filter of a query.
+/// See https://developers.notion.com/reference/post-database-query-filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryFilter {
+    /// Filter object of Notion API, passed through as it is
+    Json(Value),
+    /// Expression like `Status = Done and Priority >= 2`.
+    /// It's compiled into a filter object when the schema of the database is known.
+    Expression(String),
+}
+
+impl QueryFilter {
+    /// Filter object to be sent to Notion API
+    pub fn to_json(&self, schema: &NotionDatabaseSchema) -> Result<Value> {
+        match self {
+            QueryFilter::Json(filter) => Ok(filter.clone()),
+            QueryFilter::Expression(expression) => compile_filter_expression(expression, schema),
+        }
+    }
+}
+
+impl FromStr for QueryFilter {
+    type Err = Error;
+
+    /// Parse as filter JSON if it looks like a JSON object, otherwise as an expression
+    fn from_str(s: &str) -> Result<QueryFilter> {
+        if s.trim_start().starts_with('{') {
+            let filter = serde_json::from_str::<Value>(s).map_err(|err| {
+                Error::InvalidArgument(format!("Filter is not valid JSON: {}", err))
+            })?;
+            Ok(QueryFilter::Json(filter))
+        } else {
+            Ok(QueryFilter::Expression(s.to_string()))
+        }
+    }
+}
+
+/// Sort of a query.
+/// See https://developers.notion.com/reference/post-database-query-sort
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySort {
+    /// Property name, or "created_time" / "last_edited_time" to sort by timestamps of pages
+    pub key: String,
+    pub ascending: bool,
+}
+
+impl QuerySort {
+    pub fn to_json(&self) -> Value {
+        let direction = if self.ascending {
+            "ascending"
+        } else {
+            "descending"
+        };
+        match self.key.as_str() {
+            "created_time" | "last_edited_time" => {
+                json!({"timestamp": self.key, "direction": direction})
+            }
+            _ => json!({"property": self.key, "direction": direction}),
+        }
+    }
+}
+
+impl FromStr for QuerySort {
+    type Err = Error;
+
+    /// Parse a sort like "Due", "Due:desc" or "created_time:ascending".
+    /// The suffix is taken as a direction only if it is one, so that a property name can include ":".
+    fn from_str(s: &str) -> Result<QuerySort> {
+        let direction = |suffix: &str| match suffix.trim().to_lowercase().as_str() {
+            "asc" | "ascending" => Some(true),
+            "desc" | "descending" => Some(false),
+            _ => None,
+        };
+        let (key, ascending) = match s.rsplit_once(':') {
+            Some((key, suffix)) => match direction(suffix) {
+                Some(ascending) => (key, ascending),
+                None => (s, true),
+            },
+            None => (s, true),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(Error::InvalidArgument(format!(
+                r#"Sort "{}" has no property name"#,
+                s
+            )));
+        }
+        Ok(QuerySort {
+            key: key.to_string(),
+            ascending,
+        })
+    }
+}
+
+/// Filter and sorts applied to queries of pages
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryOptions {
+    pub filter: Option<QueryFilter>,
+    /// Pages are sorted by created_time ascending if empty
+    pub sorts: Vec<QuerySort>,
+}
+
+impl QueryOptions {
+    /// Fields of a query request body except pagination
+    pub fn to_json(&self, schema: &NotionDatabaseSchema) -> Result<Value> {
+        let sorts = if self.sorts.is_empty() {
+            vec![json!({"timestamp": "created_time", "direction": "ascending"})]
+        } else {
+            self.sorts.iter().map(|sort| sort.to_json()).collect()
+        };
+        let mut query = json!({ "sorts": sorts });
+        if let Some(filter) = &self.filter {
+            query["filter"] = filter.to_json(schema)?;
+        }
+        Ok(query)
+    }
+}
+
+/// Operators of filter expressions. Word operators need surrounding spaces, symbols don't.
+/// Longer ones come first so that ">=" isn't taken as ">".
+static OPERATORS: [&str; 10] = [
+    " is not empty",
+    " is empty",
+    " not contains ",
+    " contains ",
+    "!=",
+    ">=",
+    "<=",
+    "=",
+    ">",
+    "<",
+];
+
+/// Compile an expression like `Status = Done and Priority >= 2` into a filter object.
+/// Conditions are joined with either "and" or "or", and each one is `<property> <operator> <value>`
+/// where the operator is one of `=`, `!=`, `>`, `>=`, `<`, `<=`, `contains`, `not contains`,
+/// `is empty` and `is not empty`. Spaces around symbol operators are optional, e.g. `Points>=3`.
+/// The value can be double quoted.
+pub fn compile_filter_expression(expression: &str, schema: &NotionDatabaseSchema) -> Result<Value> {
+    let and_positions = find_unquoted(expression, " and ");
+    let or_positions = find_unquoted(expression, " or ");
+    if !and_positions.is_empty() && !or_positions.is_empty() {
+        return Err(Error::InvalidArgument(
+            r#"Filter expression can't mix "and" and "or""#.to_string(),
+        ));
+    }
+    let (positions, compound) = if or_positions.is_empty() {
+        (and_positions, "and")
+    } else {
+        (or_positions, "or")
+    };
+    let separator_len = compound.len() + 2;
+    let mut conditions = vec![];
+    let mut start = 0;
+    for position in positions {
+        conditions.push(&expression[start..position]);
+        start = position + separator_len;
+    }
+    conditions.push(&expression[start..]);
+
+    let mut filters = conditions
+        .iter()
+        .map(|condition| compile_condition(condition.trim(), schema))
+        .collect::<Result<Vec<_>>>()?;
+    if filters.len() == 1 {
+        Ok(filters.remove(0))
+    } else {
+        Ok(json!({ compound: filters }))
+    }
+}
+
+fn compile_condition(condition: &str, schema: &NotionDatabaseSchema) -> Result<Value> {
+    let invalid = |message: &str| {
+        Error::InvalidArgument(format!(
+            r#"Invalid filter condition "{}": {}"#,
+            condition, message
+        ))
+    };
+
+    // Pad so that " is empty" at the end of the condition is found
+    let padded = format!("{} ", condition);
+    let (position, operator) = OPERATORS
+        .iter()
+        .filter_map(|operator| {
+            find_unquoted(&padded, operator)
+                .first()
+                .map(|position| (*position, *operator))
+        })
+        .min_by_key(|(position, operator)| (*position, usize::MAX - operator.len()))
+        .ok_or_else(|| invalid("no operator is found"))?;
+    let name = unquote(padded[..position].trim());
+    let value = unquote(padded[position + operator.len()..].trim());
+    let operator = operator.trim();
+
+    let property = schema
+        .properties
+        .get(name)
+        .ok_or_else(|| invalid(&format!(r#"property "{}" doesn't exist"#, name)))?;
+
+    let (filter_key, filter_value) = match operator {
+        "is empty" => ("is_empty", json!(true)),
+        "is not empty" => ("is_not_empty", json!(true)),
+        _ => {
+            let filter_key = condition_key(&property.property_type, operator).ok_or_else(|| {
+                invalid(&format!(
+                    "{} property doesn't support \"{}\"",
+                    property.property_raw_type, operator
+                ))
+            })?;
+            let filter_value = match property.property_type {
+                NotionPropertyType::Number => json!(value
+                    .parse::<f64>()
+                    .map_err(|_| invalid(&format!(r#""{}" is not a number"#, value)))?),
+                NotionPropertyType::Checkbox => json!(value
+                    .parse::<bool>()
+                    .map_err(|_| invalid(&format!(r#""{}" is not true or false"#, value)))?),
+                _ => json!(value),
+            };
+            (filter_key, filter_value)
+        }
+    };
+    Ok(json!({
+        "property": name,
+        property.property_raw_type.as_str(): { filter_key: filter_value },
+    }))
+}
+
+/// Condition key of Notion filter for the operator, or None if the property type doesn't support it
+fn condition_key(property_type: &NotionPropertyType, operator: &str) -> Option<&'static str> {
+    use NotionPropertyType::*;
+    match property_type {
+        Title | RichText | Url | Email | PhoneNumber => match operator {
+            "=" => Some("equals"),
+            "!=" => Some("does_not_equal"),
+            "contains" => Some("contains"),
+            "not contains" => Some("does_not_contain"),
+            _ => None,
+        },
+        Number => match operator {
+            "=" => Some("equals"),
+            "!=" => Some("does_not_equal"),
+            ">" => Some("greater_than"),
+            ">=" => Some("greater_than_or_equal_to"),
+            "<" => Some("less_than"),
+            "<=" => Some("less_than_or_equal_to"),
+            _ => None,
+        },
+        Checkbox | Select | Status => match operator {
+            "=" => Some("equals"),
+            "!=" => Some("does_not_equal"),
+            _ => None,
+        },
+        MultiSelect | People | Relation => match operator {
+            "=" | "contains" => Some("contains"),
+            "!=" | "not contains" => Some("does_not_contain"),
+            _ => None,
+        },
+        Date | CreatedTime | LastEditedTime => match operator {
+            "=" => Some("equals"),
+            ">" => Some("after"),
+            ">=" => Some("on_or_after"),
+            "<" => Some("before"),
+            "<=" => Some("on_or_before"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Byte positions of the pattern in the string outside double quotes, so that quoted values can include operators
+fn find_unquoted(s: &str, pattern: &str) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut quoted = false;
+    let mut position = 0;
+    while position < s.len() {
+        if s[position..].starts_with('"') {
+            quoted = !quoted;
+        } else if !quoted && s[position..].starts_with(pattern) {
+            positions.push(position);
+            position += pattern.len();
+            continue;
+        }
+        position += s[position..].chars().next().map_or(1, |c| c.len_utf8());
+    }
+    positions
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notion_database::parse_database_schema;
+
+    fn schema() -> NotionDatabaseSchema {
+        let data = json!({
+            "object": "database",
+            "id": "xxxx",
+            "title": [],
+            "properties": {
+                "Name": {"id": "title", "name": "Name", "type": "title", "title": {}},
+                "Status": {"id": "s", "name": "Status", "type": "status", "status": {"options": [], "groups": []}},
+                "Priority": {"id": "p", "name": "Priority", "type": "number", "number": {}},
+                "Due date": {"id": "d", "name": "Due date", "type": "date", "date": {}},
+                "Tags": {"id": "t", "name": "Tags", "type": "multi_select", "multi_select": {"options": []}},
+                "Done": {"id": "c", "name": "Done", "type": "checkbox", "checkbox": {}}
+            }
+        });
+        parse_database_schema(&data).unwrap()
+    }
+
+    #[test]
+    fn test_compile_filter_expression() {
+        let schema = schema();
+        assert_eq!(
+            compile_filter_expression("Status = Done", &schema).unwrap(),
+            json!({"property": "Status", "status": {"equals": "Done"}})
+        );
+        assert_eq!(
+            compile_filter_expression(r#"Due date >= 2024-04-01 and Priority > 2"#, &schema)
+                .unwrap(),
+            json!({"and": [
+                {"property": "Due date", "date": {"on_or_after": "2024-04-01"}},
+                {"property": "Priority", "number": {"greater_than": 2.0}},
+            ]})
+        );
+        assert_eq!(
+            compile_filter_expression(r#"Tags contains "In progress" or Done = true"#, &schema)
+                .unwrap(),
+            json!({"or": [
+                {"property": "Tags", "multi_select": {"contains": "In progress"}},
+                {"property": "Done", "checkbox": {"equals": true}},
+            ]})
+        );
+        assert_eq!(
+            compile_filter_expression(
+                r#"Tags contains "Research and development" and Name = "A or B""#,
+                &schema
+            )
+            .unwrap(),
+            json!({"and": [
+                {"property": "Tags", "multi_select": {"contains": "Research and development"}},
+                {"property": "Name", "title": {"equals": "A or B"}},
+            ]})
+        );
+        assert_eq!(
+            compile_filter_expression(r#"Name = "x > y""#, &schema).unwrap(),
+            json!({"property": "Name", "title": {"equals": "x > y"}})
+        );
+        assert_eq!(
+            compile_filter_expression("Status=Done", &schema).unwrap(),
+            json!({"property": "Status", "status": {"equals": "Done"}})
+        );
+        assert_eq!(
+            compile_filter_expression(
+                "Priority>=3 and Priority!=5 and Due date<2024-04-01",
+                &schema
+            )
+            .unwrap(),
+            json!({"and": [
+                {"property": "Priority", "number": {"greater_than_or_equal_to": 3.0}},
+                {"property": "Priority", "number": {"does_not_equal": 5.0}},
+                {"property": "Due date", "date": {"before": "2024-04-01"}},
+            ]})
+        );
+        assert_eq!(
+            compile_filter_expression("Name is not empty", &schema).unwrap(),
+            json!({"property": "Name", "title": {"is_not_empty": true}})
+        );
+    }
+
+    #[test]
+    fn test_compile_invalid_filter_expression() {
+        let schema = schema();
+        assert!(compile_filter_expression("Status", &schema).is_err());
+        assert!(compile_filter_expression("Unknown = 1", &schema).is_err());
+        assert!(compile_filter_expression("Priority = high", &schema).is_err());
+        assert!(compile_filter_expression("Status > Done", &schema).is_err());
+        assert!(
+            compile_filter_expression("Done = true and Priority = 1 or Priority = 2", &schema)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        assert_eq!(
+            "Due date".parse::<QuerySort>().unwrap().to_json(),
+            json!({"property": "Due date", "direction": "ascending"})
+        );
+        assert_eq!(
+            "last_edited_time:desc"
+                .parse::<QuerySort>()
+                .unwrap()
+                .to_json(),
+            json!({"timestamp": "last_edited_time", "direction": "descending"})
+        );
+        assert_eq!(
+            "Due: date".parse::<QuerySort>().unwrap(),
+            QuerySort {
+                key: "Due: date".to_string(),
+                ascending: true
+            }
+        );
+        assert_eq!(
+            "Due: date:DESC".parse::<QuerySort>().unwrap(),
+            QuerySort {
+                key: "Due: date".to_string(),
+                ascending: false
+            }
+        );
+        assert!(":asc".parse::<QuerySort>().is_err());
+    }
+
+    #[test]
+    fn test_query_options() {
+        let schema = schema();
+        assert_eq!(
+            QueryOptions::default().to_json(&schema).unwrap(),
+            json!({"sorts": [{"timestamp": "created_time", "direction": "ascending"}]})
+        );
+
+        let options = QueryOptions {
+            filter: Some(
+                r#"{"property": "Done", "checkbox": {"equals": true}}"#
+                    .parse()
+                    .unwrap(),
+            ),
+            sorts: vec!["Priority:desc".parse().unwrap()],
+        };
+        assert_eq!(
+            options.to_json(&schema).unwrap(),
+            json!({
+                "sorts": [{"property": "Priority", "direction": "descending"}],
+                "filter": {"property": "Done", "checkbox": {"equals": true}},
+            })
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Notion recommends an average of three requests per second
+pub static DEFAULT_REQUESTS_PER_SECOND: f64 = 3.0;
+
+/// How long requests waited for the rate limiter, and how many of them were sent again
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimiterMetrics {
+    /// Number of requests which went through the limiter, including retries
+    pub requests: u64,
+    /// Number of requests which were sent again since Notion API was rate limited or unavailable
+    pub retries: u64,
+    /// Number of requests which had to wait
+    pub waited_requests: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens. It gets negative when requests are queued waiting for tokens.
+    tokens: f64,
+    last_refill: Instant,
+    metrics: RateLimiterMetrics,
+}
+
+/// Token bucket rate limiter shared by all requests of a client.
+/// Tokens are refilled at `requests_per_second`, and up to one second worth of tokens can be stored,
+/// which allows short bursts while keeping the average rate.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a limiter. `requests_per_second` of 0 or less disables limiting.
+    pub fn new(requests_per_second: f64) -> RateLimiter {
+        let capacity = requests_per_second.max(1.0);
+        RateLimiter {
+            requests_per_second,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                metrics: RateLimiterMetrics::default(),
+            }),
+        }
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// Take a token and return how long the caller has to wait before sending the request
+    pub fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.metrics.requests += 1;
+        if self.requests_per_second <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.capacity);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        let wait = Duration::from_secs_f64(-bucket.tokens / self.requests_per_second);
+        bucket.metrics.waited_requests += 1;
+        bucket.metrics.total_wait += wait;
+        bucket.metrics.max_wait = bucket.metrics.max_wait.max(wait);
+        wait
+    }
+
+    /// Block the current thread until a request is allowed
+    pub fn acquire_blocking(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            debug!("Waiting {:?} for rate limit", wait);
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Wait until a request is allowed
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            debug!("Waiting {:?} for rate limit", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Count a request which is going to be sent again
+    pub fn record_retry(&self) {
+        self.bucket.lock().unwrap().metrics.retries += 1;
+    }
+
+    pub fn metrics(&self) -> RateLimiterMetrics {
+        self.bucket.lock().unwrap().metrics.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(2.0);
+        // Bucket starts full, so a burst of 2 requests doesn't wait
+        assert!(limiter.reserve().is_zero());
+        assert!(limiter.reserve().is_zero());
+
+        let wait = limiter.reserve();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+        let wait = limiter.reserve();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_millis(1000));
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.requests, 4);
+        assert_eq!(metrics.waited_requests, 2);
+        assert!(metrics.max_wait > Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = RateLimiter::new(0.0);
+        for _ in 0..100 {
+            assert!(limiter.reserve().is_zero());
+        }
+        assert_eq!(limiter.metrics().requests, 100);
+        assert_eq!(limiter.metrics().waited_requests, 0);
+    }
+}
@@ -1,48 +1,349 @@
-use std::{collections::HashMap, fs, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use crate::error::{Error, Result};
 use crate::{
-    notion_database::{NotionDatabaseSchema, NotionPropertyType},
+    diff::ExistingTable,
+    exporter::{Checkpoint, DatabaseRecord, Sink, SyncRun},
+    notion_comments::NotionComment,
+    notion_database::{NotionDatabaseSchema, NotionProperty, NotionPropertyType},
     notion_pages::{NotionPage, NotionPropertyValue},
 };
-use anyhow::{anyhow, Result};
-use rusqlite::{params, params_from_iter, Connection};
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension};
 
 pub static PAGE_METADATA_TABLE: &str = "page_metadata";
 pub static PAGE_PROPERTIES_TABLE: &str = "pages";
 pub static PAGE_ID_COLUMN: &str = "page_id";
+pub static STATUS_OPTIONS_TABLE: &str = "status_options";
+pub static DATABASES_TABLE: &str = "databases";
+pub static COMMENTS_TABLE: &str = "comments";
+pub static SYNC_RUNS_TABLE: &str = "sync_runs";
+pub static CHECKPOINTS_TABLE: &str = "checkpoints";
 
-/// Resolve SQLite's column name from Notion's property name
-struct ColumnNames {
+/// Resolve column names from Notion's property names.
+/// Columns of page properties tables are the page id followed by the properties in this order,
+/// which is shared by all sinks.
+pub struct ColumnNames {
     hash: HashMap<String, String>,
+    property_names: Vec<String>,
 }
 impl ColumnNames {
-    fn new(schema: &NotionDatabaseSchema) -> ColumnNames {
+    pub fn new(schema: &NotionDatabaseSchema) -> ColumnNames {
         let mut hash = HashMap::new();
         for property in schema.properties.values() {
             let column = property.name.replace('"', "\"\"");
             hash.insert(property.name.to_string(), column);
         }
-        ColumnNames { hash }
+        let mut property_names = schema.properties.keys().cloned().collect::<Vec<_>>();
+        property_names.sort();
+        ColumnNames {
+            hash,
+            property_names,
+        }
     }
 
     /// Resolve SQLite's column name
-    fn resolve(&self, notion_property_name: &str) -> &str {
+    pub fn resolve(&self, notion_property_name: &str) -> &str {
         self.hash.get(notion_property_name).unwrap()
     }
+
+    /// Names of the properties in the order of columns
+    pub fn property_names(&self) -> Vec<&str> {
+        self.property_names
+            .iter()
+            .map(|name| name.as_str())
+            .collect()
+    }
+
+    /// Names of all columns including the page id column, e.g. a header of CSV
+    pub fn header(&self) -> Vec<&str> {
+        let mut header = vec![PAGE_ID_COLUMN];
+        header.extend(self.property_names());
+        header
+    }
+
+    /// Values of the page in the order of `header()`.
+    /// Properties missing in the page are Null, so that values emptied in Notion are cleared as well.
+    pub fn row<'a>(&self, page: &'a NotionPage) -> Vec<Cow<'a, NotionPropertyValue>> {
+        let mut row = vec![Cow::Owned(NotionPropertyValue::Text(page.id.clone()))];
+        row.extend(self.property_names.iter().map(|name| {
+            page.properties
+                .get(name)
+                .map(Cow::Borrowed)
+                .unwrap_or(Cow::Owned(NotionPropertyValue::Null))
+        }));
+        row
+    }
+}
+
+/// Columns of the page metadata table, which is shared by all sinks
+pub static PAGE_METADATA_COLUMNS: [&str; 7] = [
+    "id",
+    "url",
+    "created_time",
+    "created_by",
+    "last_edited_time",
+    "last_edited_by",
+    "archived",
+];
+/// Columns of the databases table, which is shared by all sinks
+pub static DATABASES_COLUMNS: [&str; 6] = [
+    "table_name",
+    "database_id",
+    "data_source_id",
+    "title",
+    "parent_type",
+    "parent_id",
+];
+/// Columns of the comments table, which is shared by all sinks
+pub static COMMENTS_COLUMNS: [&str; 8] = [
+    "id",
+    "page_id",
+    "block_id",
+    "discussion_id",
+    "author_id",
+    "created_time",
+    "rich_text",
+    "plain_text",
+];
+
+/// Values of the page metadata in the order of `PAGE_METADATA_COLUMNS`
+pub fn page_metadata_row(page: &NotionPage) -> Vec<NotionPropertyValue> {
+    vec![
+        NotionPropertyValue::Text(page.id.clone()),
+        NotionPropertyValue::Text(page.url.clone()),
+        NotionPropertyValue::Text(page.created_time.clone()),
+        NotionPropertyValue::Json(page.created_by.clone()),
+        NotionPropertyValue::Text(page.last_edited_time.clone()),
+        NotionPropertyValue::Json(page.last_edited_by.clone()),
+        NotionPropertyValue::Boolean(page.archived),
+    ]
+}
+
+/// Values of the database in the order of `DATABASES_COLUMNS`
+pub fn database_row(database: &DatabaseRecord) -> Vec<NotionPropertyValue> {
+    vec![
+        NotionPropertyValue::Text(database.table_name.clone()),
+        NotionPropertyValue::Text(database.database_id.clone()),
+        optional_text(&database.data_source_id),
+        NotionPropertyValue::Text(database.title.clone()),
+        NotionPropertyValue::Text(database.parent.parent_type.clone()),
+        optional_text(&database.parent.id),
+    ]
+}
+
+/// Values of the comment in the order of `COMMENTS_COLUMNS`
+pub fn comment_row(comment: &NotionComment) -> Vec<NotionPropertyValue> {
+    vec![
+        NotionPropertyValue::Text(comment.id.clone()),
+        NotionPropertyValue::Text(comment.page_id.clone()),
+        optional_text(&comment.block_id),
+        NotionPropertyValue::Text(comment.discussion_id.clone()),
+        optional_text(&comment.author_id),
+        NotionPropertyValue::Text(comment.created_time.clone()),
+        NotionPropertyValue::Json(comment.rich_text.clone()),
+        NotionPropertyValue::Text(comment.plain_text.clone()),
+    ]
+}
+
+fn optional_text(value: &Option<String>) -> NotionPropertyValue {
+    match value {
+        Some(value) => NotionPropertyValue::Text(value.clone()),
+        None => NotionPropertyValue::Null,
+    }
+}
+
+/// Resolve table names of page properties for each schema.
+/// A single schema is stored into "pages" as before. When there are multiple schemas, i.e. multiple databases
+/// or a database with multiple data sources, each of them is stored into its own table like "pages_tasks"
+/// named after the data source or the database.
+pub fn properties_table_names<'a>(
+    schemas: impl IntoIterator<Item = &'a NotionDatabaseSchema>,
+) -> Vec<String> {
+    let schemas = schemas.into_iter().collect::<Vec<_>>();
+    if schemas.len() <= 1 {
+        return schemas
+            .iter()
+            .map(|_| PAGE_PROPERTIES_TABLE.to_string())
+            .collect();
+    }
+
+    let mut table_names: Vec<String> = vec![];
+    for schema in schemas {
+        let (name, id) = match &schema.data_source {
+            Some(data_source) => (data_source.name.as_str(), data_source.id.as_str()),
+            None => (schema.title.as_str(), schema.id.as_str()),
+        };
+        let sanitized_name = sanitize_table_name(name);
+        let sanitized_id = sanitize_table_name(id);
+        let mut table_name = if sanitized_name.is_empty() {
+            format!("{}_{}", PAGE_PROPERTIES_TABLE, sanitized_id)
+        } else {
+            format!("{}_{}", PAGE_PROPERTIES_TABLE, sanitized_name)
+        };
+        if table_names.contains(&table_name) {
+            table_name = format!("{}_{}", table_name, sanitized_id);
+        }
+        table_names.push(table_name);
+    }
+    table_names
+}
+
+/// Convert into a name usable as an unquoted SQLite identifier
+fn sanitize_table_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+/// SQLite type of the column for the property
+pub fn sqlite_type(property_type: &NotionPropertyType) -> &'static str {
+    match property_type {
+        NotionPropertyType::Number => "REAL",
+        NotionPropertyType::Checkbox => "BOOLEAN",
+        _ => "TEXT",
+    }
+}
+
+/// CREATE TABLE statements which `Sqlite::create_tables` runs for the schema:
+/// the page properties table, the page metadata table shared among all page properties tables,
+/// and the lookup table of status options if the database has any status property.
+pub fn create_table_statements(schema: &NotionDatabaseSchema, table_name: &str) -> Vec<String> {
+    let mut columns = vec![format!("{} TEXT PRIMARY KEY", PAGE_ID_COLUMN)];
+    columns.extend(column_definitions(schema));
+    let mut statements = vec![create_table_sql(table_name, &columns)];
+    statements.push(create_table_sql(
+        PAGE_METADATA_TABLE,
+        &[
+            "id TEXT PRIMARY KEY",
+            "url TEXT",
+            "created_time TEXT",
+            "created_by JSON",
+            "last_edited_time TEXT",
+            "last_edited_by JSON",
+            "archived BOOLEAN",
+        ],
+    ));
+    if !status_properties(schema).is_empty() {
+        statements.push(create_table_sql(
+            STATUS_OPTIONS_TABLE,
+            &[
+                "table_name TEXT",
+                "property TEXT",
+                "option TEXT",
+                "group_name TEXT",
+                "PRIMARY KEY (table_name, property, option)",
+            ],
+        ));
+    }
+    statements
+}
+
+/// Name of the table which keeps every version of pages in the page properties table, e.g. "pages_history"
+pub fn history_table_name(table_name: &str) -> String {
+    format!("{}_history", table_name)
+}
+
+/// CREATE TABLE statement of the history table of the page properties table.
+/// It has the same property columns, and each row is a version of a page which was current
+/// from `_valid_from` until `_valid_to`. `_valid_to` is NULL for the current version.
+/// Columns of the versions are prefixed with an underscore so that they don't collide with properties.
+pub fn create_history_table_statement(schema: &NotionDatabaseSchema, table_name: &str) -> String {
+    let column_names = ColumnNames::new(schema);
+    let mut columns = vec![format!("{} TEXT NOT NULL", PAGE_ID_COLUMN)];
+    columns.extend(column_names.property_names().into_iter().map(|name| {
+        let property = &schema.properties[name];
+        format!(
+            r#""{}" {}"#,
+            column_names.resolve(name),
+            sqlite_type(&property.property_type)
+        )
+    }));
+    columns.extend(
+        [
+            "_last_edited_time TEXT",
+            "_valid_from TEXT NOT NULL",
+            "_valid_to TEXT",
+            "_synced_at TEXT",
+        ]
+        .map(String::from),
+    );
+    columns.push(format!("PRIMARY KEY ({}, _valid_from)", PAGE_ID_COLUMN));
+    create_table_sql(&history_table_name(table_name), &columns)
+}
+
+fn create_table_sql(table_name: &str, columns: &[impl AsRef<str>]) -> String {
+    let columns = columns
+        .iter()
+        .map(|column| column.as_ref())
+        .collect::<Vec<_>>();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (\n    {}\n)",
+        table_name,
+        columns.join(",\n    ")
+    )
+}
+
+/// Column definitions of the properties like `"Age" REAL`, in the order of `ColumnNames::property_names()`
+fn column_definitions(schema: &NotionDatabaseSchema) -> Vec<String> {
+    let column_names = ColumnNames::new(schema);
+    column_names
+        .property_names()
+        .into_iter()
+        .map(|name| {
+            let property = &schema.properties[name];
+            let column = column_names.resolve(name);
+            let data_type = sqlite_type(&property.property_type);
+            format!(r#""{column}" {data_type}"#)
+        })
+        .collect()
+}
+
+fn status_properties(schema: &NotionDatabaseSchema) -> Vec<&NotionProperty> {
+    schema
+        .properties
+        .values()
+        .filter(|property| property.property_type == NotionPropertyType::Status)
+        .collect()
 }
 
 pub struct Sqlite<'a> {
     pub conn: Connection,
     pub schema: &'a NotionDatabaseSchema,
+    /// Name of the page properties table
+    pub table_name: String,
     column_names: ColumnNames,
 }
 impl Sqlite<'_> {
     pub fn new<'a>(path: &str, schema: &'a NotionDatabaseSchema) -> Result<Sqlite<'a>> {
+        Sqlite::new_with_table_name(path, schema, PAGE_PROPERTIES_TABLE)
+    }
+
+    pub fn new_with_table_name<'a>(
+        path: &str,
+        schema: &'a NotionDatabaseSchema,
+        table_name: &str,
+    ) -> Result<Sqlite<'a>> {
         let conn = Connection::open(path)?;
         let column_names = ColumnNames::new(schema);
         Ok(Sqlite {
             conn,
             schema,
+            table_name: table_name.to_string(),
             column_names,
         })
     }
@@ -50,69 +351,93 @@ impl Sqlite<'_> {
     /// Check if database file can be created
     pub fn validate_database_path(path: &str) -> Result<()> {
         if Path::new(path).exists() {
-            return Err(anyhow!("{} already exists", path));
+            return Err(Error::Output {
+                path: path.to_string(),
+                message: "already exists".to_string(),
+            });
         }
 
-        let conn = Connection::open(path)?;
+        let output_error = |err: rusqlite::Error| Error::Output {
+            path: path.to_string(),
+            message: err.to_string(),
+        };
+        let conn = Connection::open(path).map_err(output_error)?;
         match conn.close() {
             Ok(_) => {
                 // Delete file created by the connection because Connection::open() is just used for validation
                 fs::remove_file(path).ok();
                 Ok(())
             }
-            Err((_, err)) => Err(anyhow!(err.to_string())),
+            Err((_, err)) => Err(output_error(err)),
         }
     }
 
+    /// Create the tables, and add columns for properties which an existing page properties table doesn't have yet
     pub fn create_tables(&self) -> Result<()> {
-        // Create page properties table
-        let table_definition = self.table_definitin_from();
-        let sql = format!(
-            "CREATE TABLE {table_name} (
-                {id_column} TEXT PRIMARY KEY,
-                {definition}
-            )",
-            table_name = PAGE_PROPERTIES_TABLE,
-            id_column = PAGE_ID_COLUMN,
-            definition = table_definition,
-        );
-        debug!("{}", sql);
-        self.conn.execute(&sql, [])?;
+        for sql in create_table_statements(self.schema, &self.table_name) {
+            debug!("{}", sql);
+            self.conn.execute(&sql, [])?;
+        }
+        self.add_missing_columns(&self.table_name)?;
+        self.insert_status_options()?;
+        Ok(())
+    }
+
+    /// Add columns of properties which the table doesn't have, such as those added to the database
+    /// after the table was created
+    fn add_missing_columns(&self, table_name: &str) -> Result<()> {
+        let mut statement = self
+            .conn
+            .prepare(&format!("PRAGMA table_info({})", table_name))?;
+        let columns = statement
+            .query_map([], |row| row.get::<_, String>("name"))?
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+        for name in self.column_names.property_names() {
+            if columns.contains(name) {
+                continue;
+            }
+            let sql = format!(
+                r#"ALTER TABLE {} ADD COLUMN "{}" {}"#,
+                table_name,
+                self.column_names.resolve(name),
+                sqlite_type(&self.schema.properties[name].property_type)
+            );
+            debug!("{}", sql);
+            self.conn.execute(&sql, [])?;
+        }
+        Ok(())
+    }
+
+    /// Fill the lookup table of status options and their groups (e.g. "Done" belongs to "Complete")
+    fn insert_status_options(&self) -> Result<()> {
+        let status_properties = status_properties(self.schema);
+        if status_properties.is_empty() {
+            return Ok(());
+        }
 
-        // Create page metadata table
         let sql = format!(
-            "CREATE TABLE {table_name} (
-                id TEXT PRIMARY KEY,
-                url TEXT,
-                created_time TEXT,
-                created_by JSON,
-                last_edited_time TEXT,
-                last_edited_by JSON,
-                archived BOOLEAN
-            )",
-            table_name = PAGE_METADATA_TABLE,
+            "INSERT OR REPLACE INTO {table_name} (table_name, property, option, group_name) VALUES (?1, ?2, ?3, ?4)",
+            table_name = STATUS_OPTIONS_TABLE,
         );
-        debug!("{}", sql);
-        self.conn.execute(&sql, [])?;
+        for property in status_properties {
+            for (option, group_name) in &property.status_groups {
+                self.conn.execute(
+                    &sql,
+                    params![self.table_name, property.name, option, group_name],
+                )?;
+            }
+        }
         Ok(())
     }
 
+    /// Insert the page, or overwrite the row if the page already exists.
+    /// Every property in the schema is written, and properties missing in the page are stored as NULL,
+    /// so that values emptied in Notion are cleared as well.
     pub fn insert(&self, page: &NotionPage) -> Result<()> {
         // Insert properties of page
-        let mut property_names = vec![PAGE_ID_COLUMN];
-        for name in page.properties.keys() {
-            property_names.push(name);
-        }
-        let sql = self.create_insert_sql_for(&property_names);
+        let sql = self.create_insert_sql_for(&self.column_names.header());
         debug!("{}", sql);
-        let page_id = NotionPropertyValue::Text(page.id.clone());
-        let sql_params = params_from_iter(property_names.iter().map(|&column| {
-            if column == PAGE_ID_COLUMN {
-                &page_id
-            } else {
-                page.properties.get(column).unwrap()
-            }
-        }));
+        let sql_params = params_from_iter(self.column_names.row(page));
         debug!("Parameters: {:?}", sql_params);
         self.conn.execute(&sql, sql_params)?;
 
@@ -126,7 +451,14 @@ impl Sqlite<'_> {
                 last_edited_time,
                 last_edited_by,
                 archived
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (id) DO UPDATE SET
+                url = excluded.url,
+                created_time = excluded.created_time,
+                created_by = excluded.created_by,
+                last_edited_time = excluded.last_edited_time,
+                last_edited_by = excluded.last_edited_by,
+                archived = excluded.archived",
             table_name = PAGE_METADATA_TABLE,
         );
         let sql_params = params![
@@ -143,30 +475,144 @@ impl Sqlite<'_> {
         Ok(())
     }
 
-    /// Get table definistion string from the schema object.
-    /// It's a part of SQL query specified in {{}}:
-    /// CREATE TABLE notion (page_id TEXT PRIMARY KEY, {{"Animal" TEXT, "Age" REAL, "Name" TEXT}})
-    fn table_definitin_from(&self) -> String {
-        self.schema
-            .properties
-            .iter()
-            .map(|(_, property)| {
-                let column = self.column_names.resolve(&property.name);
-                let data_type = match property.property_type {
-                    NotionPropertyType::Title => "TEXT",
-                    NotionPropertyType::Number => "REAL",
-                    NotionPropertyType::Select => "TEXT",
-                    NotionPropertyType::Checkbox => "BOOLEAN",
-                    NotionPropertyType::Other => "TEXT",
-                    _ => "TEXT",
-                };
-                format!(r#""{column}" {data_type}"#)
+    /// Insert pages in a single transaction
+    pub fn insert_all(&self, pages: &[NotionPage]) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        for page in pages {
+            self.insert(page)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Delete the pages from the page properties table and the page metadata table
+    pub fn delete_all(&self, page_ids: &[String]) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let statements = [
+            format!(
+                "DELETE FROM {} WHERE {} = ?1",
+                self.table_name, PAGE_ID_COLUMN
+            ),
+            format!("DELETE FROM {} WHERE id = ?1", PAGE_METADATA_TABLE),
+        ];
+        for sql in statements {
+            for page_id in page_ids {
+                self.conn.execute(&sql, [page_id])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Create the history table, and add columns for properties which an existing one doesn't have yet
+    pub fn create_history_table(&self) -> Result<()> {
+        let sql = create_history_table_statement(self.schema, &self.table_name);
+        debug!("{}", sql);
+        self.conn.execute(&sql, [])?;
+        self.add_missing_columns(&history_table_name(&self.table_name))?;
+        Ok(())
+    }
+
+    /// Close the current versions of the pages, which are no longer in the database, in the history table.
+    /// They are closed at the time of the sync since when they were deleted isn't known.
+    pub fn close_history(&self, page_ids: &[String]) -> Result<()> {
+        let sql = format!(
+            "UPDATE {} SET _valid_to = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE {} = ?1 AND _valid_to IS NULL",
+            history_table_name(&self.table_name),
+            PAGE_ID_COLUMN
+        );
+        let transaction = self.conn.unchecked_transaction()?;
+        for page_id in page_ids {
+            self.conn.execute(&sql, [page_id])?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Append versions of pages which changed since the last sync to the history table.
+    /// A page whose last edited time differs from its current version gets a new version valid from
+    /// its last edited time, and the previous version is closed at the same time.
+    /// Returns the number of appended versions.
+    pub fn append_history(&self, pages: &[NotionPage]) -> Result<usize> {
+        let history_table = history_table_name(&self.table_name);
+        let transaction = self.conn.unchecked_transaction()?;
+        let synced_at: String =
+            self.conn
+                .query_row("SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 'now')", [], |row| {
+                    row.get(0)
+                })?;
+
+        let select_sql = format!(
+            "SELECT _last_edited_time FROM {} WHERE {} = ?1 AND _valid_to IS NULL",
+            history_table, PAGE_ID_COLUMN
+        );
+        let close_sql = format!(
+            "UPDATE {} SET _valid_to = ?2 WHERE {} = ?1 AND _valid_to IS NULL",
+            history_table, PAGE_ID_COLUMN
+        );
+        let mut columns = self
+            .column_names
+            .header()
+            .into_iter()
+            .map(|name| {
+                if name == PAGE_ID_COLUMN {
+                    name.to_string()
+                } else {
+                    format!(r#""{}""#, self.column_names.resolve(name))
+                }
             })
-            .collect::<Vec<_>>()
-            .join(", ")
+            .collect::<Vec<_>>();
+        columns.extend(
+            [
+                "_last_edited_time",
+                "_valid_from",
+                "_valid_to",
+                "_synced_at",
+            ]
+            .map(String::from),
+        );
+        let placeholders = (1..(columns.len() + 1))
+            .map(|index| format!("?{}", index))
+            .collect::<Vec<_>>();
+        let insert_sql = format!(
+            "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+            history_table,
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+
+        let mut appended = 0;
+        for page in pages {
+            let current: Option<Option<String>> = self
+                .conn
+                .query_row(&select_sql, [&page.id], |row| row.get(0))
+                .optional()?;
+            match current {
+                Some(Some(last_edited_time)) if last_edited_time == page.last_edited_time => {
+                    continue
+                }
+                Some(_) => {
+                    self.conn
+                        .execute(&close_sql, params![page.id, page.last_edited_time])?;
+                }
+                None => {}
+            }
+            let mut row = self.column_names.row(page);
+            row.extend([
+                Cow::Owned(NotionPropertyValue::Text(page.last_edited_time.clone())),
+                Cow::Owned(NotionPropertyValue::Text(page.last_edited_time.clone())),
+                Cow::Owned(NotionPropertyValue::Null),
+                Cow::Owned(NotionPropertyValue::Text(synced_at.clone())),
+            ]);
+            self.conn.execute(&insert_sql, params_from_iter(row))?;
+            appended += 1;
+        }
+        transaction.commit()?;
+        Ok(appended)
     }
 
-    /// Create sql like "INSERT INTO {} (id, title) values (?1, ?2)"
+    /// Create sql like
+    /// "INSERT INTO {} (page_id, "Name") VALUES (?1, ?2) ON CONFLICT (page_id) DO UPDATE SET "Name" = excluded."Name""
     fn create_insert_sql_for(&self, properties: &[&str]) -> String {
         let columns_formatted = properties
             .iter()
@@ -182,16 +628,371 @@ impl Sqlite<'_> {
         let placeholders = (1..(columns_formatted.len() + 1))
             .map(|index| format!("?{}", index))
             .collect::<Vec<_>>();
+        let updates = columns_formatted
+            .iter()
+            .filter(|&column| column != PAGE_ID_COLUMN)
+            .map(|column| format!("{column} = excluded.{column}"))
+            .collect::<Vec<_>>();
+        let on_conflict = if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        };
 
         format!(
-            "INSERT INTO {table_name} ({columns}) VALUES ({values})",
-            table_name = &PAGE_PROPERTIES_TABLE,
+            "INSERT INTO {table_name} ({columns}) VALUES ({values}) ON CONFLICT ({id_column}) {on_conflict}",
+            table_name = self.table_name,
             columns = columns_formatted.join(", "),
-            values = placeholders.join(", ")
+            values = placeholders.join(", "),
+            id_column = PAGE_ID_COLUMN,
+            on_conflict = on_conflict,
         )
     }
 }
 
+/// Sink which writes pages into a SQLite file
+pub struct SqliteSink {
+    pub path: String,
+    /// Keep every version of pages in history tables like "pages_history"
+    pub history: bool,
+}
+impl SqliteSink {
+    pub fn new(path: &str) -> SqliteSink {
+        SqliteSink {
+            path: path.to_string(),
+            history: false,
+        }
+    }
+
+    /// Keep every version of pages in history tables, which the pages tables only hold the current one of
+    pub fn history(mut self, history: bool) -> SqliteSink {
+        self.history = history;
+        self
+    }
+
+    /// Read columns and last edited times of pages in the table, opening the file as read-only
+    pub fn read_existing_table(&self, table_name: &str) -> Result<ExistingTable> {
+        if !Path::new(&self.path).exists() {
+            return Ok(ExistingTable::default());
+        }
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let table_exists = |name: &str| -> Result<bool> {
+            Ok(conn
+                .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?
+                .exists(params![name])?)
+        };
+        if !table_exists(table_name)? {
+            return Ok(ExistingTable::default());
+        }
+
+        let mut statement = conn.prepare(&format!("PRAGMA table_info({})", table_name))?;
+        let columns = statement
+            .query_map([], |row| row.get::<_, String>("name"))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let sql = if table_exists(PAGE_METADATA_TABLE)? {
+            format!(
+                "SELECT {id_column}, {metadata_table}.last_edited_time FROM {table_name}
+                LEFT JOIN {metadata_table} ON {metadata_table}.id = {table_name}.{id_column}",
+                id_column = PAGE_ID_COLUMN,
+                metadata_table = PAGE_METADATA_TABLE,
+                table_name = table_name,
+            )
+        } else {
+            format!("SELECT {}, NULL FROM {}", PAGE_ID_COLUMN, table_name)
+        };
+        let mut statement = conn.prepare(&sql)?;
+        let last_edited_times = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<String, Option<String>>>>()?;
+        Ok(ExistingTable {
+            exists: true,
+            columns,
+            last_edited_times,
+        })
+    }
+}
+impl Sink for SqliteSink {
+    fn create_table(&mut self, schema: &NotionDatabaseSchema, table_name: &str) -> Result<()> {
+        let sqlite = Sqlite::new_with_table_name(&self.path, schema, table_name)?;
+        sqlite.create_tables()?;
+        if self.history {
+            sqlite.create_history_table()?;
+        }
+        Ok(())
+    }
+
+    fn write_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()> {
+        let sqlite = Sqlite::new_with_table_name(&self.path, schema, table_name)?;
+        sqlite.insert_all(pages)?;
+        if self.history {
+            sqlite.append_history(pages)?;
+        }
+        Ok(())
+    }
+
+    fn delete_pages(
+        &mut self,
+        schema: &NotionDatabaseSchema,
+        table_name: &str,
+        page_ids: &[String],
+    ) -> Result<()> {
+        let sqlite = Sqlite::new_with_table_name(&self.path, schema, table_name)?;
+        sqlite.delete_all(page_ids)?;
+        if self.history {
+            sqlite.close_history(page_ids)?;
+        }
+        Ok(())
+    }
+
+    fn existing_table(&mut self, table_name: &str) -> Result<Option<ExistingTable>> {
+        Ok(Some(self.read_existing_table(table_name)?))
+    }
+
+    fn supports_checkpoints(&self) -> bool {
+        true
+    }
+
+    fn read_checkpoint(&mut self, table_name: &str) -> Result<Option<Checkpoint>> {
+        if !Path::new(&self.path).exists() {
+            return Ok(None);
+        }
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let table_exists = conn
+            .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?
+            .exists(params![CHECKPOINTS_TABLE])?;
+        if !table_exists {
+            return Ok(None);
+        }
+        let sql = format!(
+            "SELECT run_id, database_id, next_cursor, completed, query, schema, pages FROM {} WHERE table_name = ?1",
+            CHECKPOINTS_TABLE
+        );
+        let checkpoint = conn
+            .query_row(&sql, params![table_name], |row| {
+                Ok(Checkpoint {
+                    run_id: row.get::<_, i64>(0)? as u64,
+                    table_name: table_name.to_string(),
+                    database_id: row.get(1)?,
+                    next_cursor: row.get(2)?,
+                    completed: row.get(3)?,
+                    query: serde_json::from_str(&row.get::<_, String>(4)?).map_err(|err| {
+                        rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(err))
+                    })?,
+                    schema: row.get(5)?,
+                    pages: row.get::<_, i64>(6)? as usize,
+                })
+            })
+            .optional()?;
+        Ok(checkpoint)
+    }
+
+    fn write_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (
+                table_name TEXT PRIMARY KEY,
+                run_id INTEGER,
+                database_id TEXT,
+                next_cursor TEXT,
+                completed BOOLEAN,
+                query JSON,
+                schema TEXT,
+                pages INTEGER,
+                updated_at TEXT
+            )",
+            table_name = CHECKPOINTS_TABLE,
+        );
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+
+        let sql = format!(
+            "INSERT OR REPLACE INTO {} VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+            CHECKPOINTS_TABLE
+        );
+        conn.execute(
+            &sql,
+            params![
+                checkpoint.table_name,
+                checkpoint.run_id as i64,
+                checkpoint.database_id,
+                checkpoint.next_cursor,
+                checkpoint.completed,
+                serde_json::to_string(&checkpoint.query).unwrap(),
+                checkpoint.schema,
+                checkpoint.pages as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn clear_checkpoints(&mut self) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let sql = format!("DROP TABLE IF EXISTS {}", CHECKPOINTS_TABLE);
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+
+    fn write_sync_run(&mut self, run: &SyncRun) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT,
+                finished_at TEXT,
+                duration_ms INTEGER,
+                tool_version TEXT,
+                notion_version TEXT,
+                database_ids JSON,
+                pages_fetched INTEGER,
+                pages_inserted INTEGER,
+                pages_updated INTEGER,
+                pages_deleted INTEGER,
+                requests INTEGER,
+                rate_limited_requests INTEGER,
+                retries INTEGER,
+                error TEXT
+            )",
+            table_name = SYNC_RUNS_TABLE,
+        );
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+
+        let unix_time = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64()
+        };
+        let duration_ms = run
+            .finished_at
+            .duration_since(run.started_at)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let sql = format!(
+            "INSERT INTO {table_name} (
+                started_at, finished_at, duration_ms, tool_version, notion_version, database_ids,
+                pages_fetched, pages_inserted, pages_updated, pages_deleted,
+                requests, rate_limited_requests, retries, error
+            ) VALUES (
+                strftime('%Y-%m-%dT%H:%M:%fZ', ?1, 'unixepoch'),
+                strftime('%Y-%m-%dT%H:%M:%fZ', ?2, 'unixepoch'),
+                ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14
+            )",
+            table_name = SYNC_RUNS_TABLE,
+        );
+        conn.execute(
+            &sql,
+            params![
+                unix_time(run.started_at),
+                unix_time(run.finished_at),
+                duration_ms,
+                run.tool_version,
+                run.notion_version,
+                serde_json::to_string(&run.database_ids).unwrap(),
+                run.pages_fetched as i64,
+                run.pages_inserted.map(|count| count as i64),
+                run.pages_updated.map(|count| count as i64),
+                run.pages_deleted.map(|count| count as i64),
+                run.requests as i64,
+                run.rate_limited_requests as i64,
+                run.retries as i64,
+                run.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn write_databases(&mut self, databases: &[DatabaseRecord]) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (
+                table_name TEXT PRIMARY KEY,
+                database_id TEXT,
+                data_source_id TEXT,
+                title TEXT,
+                parent_type TEXT,
+                parent_id TEXT
+            )",
+            table_name = DATABASES_TABLE,
+        );
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+
+        let sql = format!(
+            "INSERT OR REPLACE INTO {table_name} (
+                table_name, database_id, data_source_id, title, parent_type, parent_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            table_name = DATABASES_TABLE,
+        );
+        let transaction = conn.unchecked_transaction()?;
+        for database in databases {
+            conn.execute(
+                &sql,
+                params![
+                    database.table_name,
+                    database.database_id,
+                    database.data_source_id,
+                    database.title,
+                    database.parent.parent_type,
+                    database.parent.id,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn write_comments(&mut self, comments: &[NotionComment]) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} (
+                id TEXT PRIMARY KEY,
+                page_id TEXT,
+                block_id TEXT,
+                discussion_id TEXT,
+                author_id TEXT,
+                created_time TEXT,
+                rich_text TEXT,
+                plain_text TEXT
+            )",
+            table_name = COMMENTS_TABLE,
+        );
+        debug!("{}", sql);
+        conn.execute(&sql, [])?;
+
+        let sql = format!(
+            "INSERT OR REPLACE INTO {table_name} (
+                id, page_id, block_id, discussion_id, author_id, created_time, rich_text, plain_text
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            table_name = COMMENTS_TABLE,
+        );
+        let transaction = conn.unchecked_transaction()?;
+        for comment in comments {
+            conn.execute(
+                &sql,
+                params![
+                    comment.id,
+                    comment.page_id,
+                    comment.block_id,
+                    comment.discussion_id,
+                    comment.author_id,
+                    comment.created_time,
+                    comment.rich_text.to_string(),
+                    comment.plain_text,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,4 +1009,57 @@ mod tests {
         let result = Sqlite::validate_database_path(invalid_path);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_sanitize_table_name() {
+        assert_eq!(sanitize_table_name("Tasks"), "tasks");
+        assert_eq!(sanitize_table_name("Q3 Roadmap (2024)"), "q3_roadmap__2024");
+        assert_eq!(sanitize_table_name("タスク"), "");
+    }
+
+    #[test]
+    fn test_create_table_statements() {
+        let database = serde_json::json!({
+            "object": "database",
+            "id": "database1",
+            "title": [{"plain_text": "Tasks"}],
+            "properties": {
+                "Name": {"id": "title", "name": "Name", "type": "title", "title": {}},
+                "Age": {"id": "a", "name": "Age", "type": "number", "number": {}},
+                "Task ID": {"id": "b", "name": "Task ID", "type": "unique_id", "unique_id": {}},
+            },
+        });
+        let schema = crate::notion_database::parse_database_schema(&database).unwrap();
+        let statements = create_table_statements(&schema, "pages");
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0],
+            "CREATE TABLE IF NOT EXISTS pages (\n    page_id TEXT PRIMARY KEY,\n    \"Age\" REAL,\n    \"Name\" TEXT,\n    \"Task ID\" TEXT\n)"
+        );
+        assert!(statements[1]
+            .starts_with("CREATE TABLE IF NOT EXISTS page_metadata (\n    id TEXT PRIMARY KEY,"));
+    }
+
+    #[test]
+    fn test_create_history_table_statement() {
+        let database = serde_json::json!({
+            "object": "database",
+            "id": "database1",
+            "title": [{"plain_text": "Tasks"}],
+            "properties": {
+                "Name": {"id": "title", "name": "Name", "type": "title", "title": {}},
+                "valid_from": {"id": "a", "name": "valid_from", "type": "date", "date": {}},
+                "synced_at": {"id": "b", "name": "synced_at", "type": "rich_text", "rich_text": {}},
+            },
+        });
+        let schema = crate::notion_database::parse_database_schema(&database).unwrap();
+        let statement = create_history_table_statement(&schema, "pages");
+        assert_eq!(
+            statement,
+            "CREATE TABLE IF NOT EXISTS pages_history (\n    page_id TEXT NOT NULL,\n    \"Name\" TEXT,\n    \"synced_at\" TEXT,\n    \"valid_from\" TEXT,\n    _last_edited_time TEXT,\n    _valid_from TEXT NOT NULL,\n    _valid_to TEXT,\n    _synced_at TEXT,\n    PRIMARY KEY (page_id, _valid_from)\n)"
+        );
+        // Properties named like the columns of versions don't collide with them
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(&statement, []).unwrap();
+    }
 }
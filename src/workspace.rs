@@ -0,0 +1,125 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::notion_client::NotionClient;
+use crate::notion_database::NotionDatabaseSchema;
+use crate::notion_id::normalize_notion_id;
+
+/// A database found by walking blocks under a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredDatabase {
+    pub id: String,
+    pub title: String,
+    /// Page which the database is placed in. None if the database itself is the root.
+    pub page_id: Option<String>,
+}
+
+/// Find all databases under the page by following "child_page" and "child_database" blocks.
+/// Blocks which have children, such as columns and toggles, are walked as well to find inline databases,
+/// and so are pages of the databases found, which may have databases nested in them.
+/// If the root is a database, it's returned along with databases nested in its pages.
+/// Databases which can't be retrieved, such as linked databases, are skipped with a warning.
+pub fn find_databases(client: &NotionClient, root_id: &str) -> Result<Vec<DiscoveredDatabase>> {
+    let root_id = normalize_notion_id(root_id)?;
+    let root = client.get_block(&root_id)?;
+
+    let mut databases: Vec<DiscoveredDatabase> = vec![];
+    let mut visited = HashSet::new();
+    // Blocks to walk, with the page which contains them
+    let mut queue = VecDeque::new();
+    if block_type(&root) == Some("child_database") {
+        // The root is given explicitly, so it's an error if it can't be retrieved
+        let schemas = client.get_database_schemas(&root_id)?;
+        queue.extend(database_pages(client, &root_id, &schemas)?);
+        visited.insert(root_id.clone());
+        databases.push(DiscoveredDatabase {
+            id: root_id.clone(),
+            title: block_title(&root, "child_database"),
+            page_id: None,
+        });
+    } else {
+        queue.push_back((root_id.clone(), root_id.clone()));
+    }
+
+    while let Some((block_id, page_id)) = queue.pop_front() {
+        if !visited.insert(block_id.clone()) {
+            continue;
+        }
+        for child in client.get_block_children(&block_id)? {
+            let child_id = match child.get("id").and_then(|id| id.as_str()) {
+                Some(child_id) => child_id.to_string(),
+                None => continue,
+            };
+            match block_type(&child) {
+                Some("child_database") if visited.insert(child_id.clone()) => {
+                    let title = block_title(&child, "child_database");
+                    let schemas = match client.get_database_schemas(&child_id) {
+                        Ok(schemas) => schemas,
+                        Err(err) => {
+                            warn!(
+                                r#"Skipped database "{}" ({}) in page {} since it can't be retrieved, e.g. as a linked database: {}"#,
+                                title, child_id, page_id, err
+                            );
+                            continue;
+                        }
+                    };
+                    info!(
+                        r#"Found database "{}" ({}) in page {}"#,
+                        title, child_id, page_id
+                    );
+                    queue.extend(database_pages(client, &child_id, &schemas)?);
+                    databases.push(DiscoveredDatabase {
+                        id: child_id,
+                        title,
+                        page_id: Some(page_id.clone()),
+                    });
+                }
+                Some("child_database") => {}
+                Some("child_page") => queue.push_back((child_id.clone(), child_id)),
+                _ if child.get("has_children").and_then(|h| h.as_bool()) == Some(true) => {
+                    queue.push_back((child_id, page_id.clone()))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if databases.is_empty() {
+        return Err(Error::InvalidArgument(format!(
+            "No database is found under page {}",
+            root_id
+        )));
+    }
+    Ok(databases)
+}
+
+/// Pages of the database to walk, each of which is the page containing its blocks
+fn database_pages(
+    client: &NotionClient,
+    database_id: &str,
+    schemas: &[NotionDatabaseSchema],
+) -> Result<Vec<(String, String)>> {
+    let mut pages = vec![];
+    for schema in schemas {
+        for page_id in client.get_page_ids(database_id, schema)? {
+            pages.push((page_id.clone(), page_id));
+        }
+    }
+    Ok(pages)
+}
+
+fn block_type(block: &Value) -> Option<&str> {
+    block.get("type").and_then(|t| t.as_str())
+}
+
+/// Title of a "child_page" or "child_database" block like `{"child_database": {"title": "Tasks"}}`
+fn block_title(block: &Value, block_type: &str) -> String {
+    block
+        .get(block_type)
+        .and_then(|b| b.get("title"))
+        .and_then(|t| t.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
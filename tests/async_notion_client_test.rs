@@ -0,0 +1,229 @@
+mod common;
+
+extern crate notion_into_sqlite;
+
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::fixtures;
+use common::helpers::before_db;
+use common::mock_server::MockServer;
+use notion_into_sqlite::async_notion_client::{AsyncNotionClient, AsyncPageSource};
+use notion_into_sqlite::exporter::Exporter;
+use notion_into_sqlite::notion_client::NotionClient;
+use notion_into_sqlite::rate_limiter::RateLimiter;
+use notion_into_sqlite::sqlite::SqliteSink;
+use serde_json::json;
+
+/// Query response of the fixture with no more pages
+fn last_page_list_json() -> String {
+    let mut list = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_JSON).unwrap();
+    list["next_cursor"] = serde_json::Value::Null;
+    list["has_more"] = false.into();
+    list.to_string()
+}
+
+#[tokio::test]
+async fn it_fetches_pages_of_multiple_databases() -> Result<(), Box<dyn Error>> {
+    let list_json = last_page_list_json();
+    let server = MockServer::start(move |request| {
+        if request.method == "GET" {
+            (200, fixtures::NOTION_DATABASE_JSON.to_string())
+        } else {
+            (200, list_json.clone())
+        }
+    });
+    let mut client = AsyncNotionClient::with_limits("secret", 4, 0.0);
+    client.base_url = server.url.clone();
+
+    let database_ids = vec![
+        "8a281474f0714c548afc17d8a4b7c781".to_string(),
+        "8a281474-f071-4c54-8afc-17d8a4b7c782".to_string(),
+    ];
+    let (databases, diagnostics) = client.get_pages_of_databases(&database_ids).await?;
+    assert!(diagnostics.is_empty());
+    assert_eq!(databases.len(), 2);
+    assert_eq!(databases[0].database_id, "8a281474f0714c548afc17d8a4b7c781");
+    assert_eq!(
+        databases[1].database_id,
+        "8a281474-f071-4c54-8afc-17d8a4b7c782"
+    );
+    assert_eq!(databases[1].pages.len(), 1);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests
+        .iter()
+        .all(|request| request.headers.get("notion-version").unwrap() == "2022-02-22"));
+    assert!(requests
+        .iter()
+        .any(|request| request.path == "/v1/databases/8a281474-f071-4c54-8afc-17d8a4b7c782/query"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_limits_concurrent_requests() -> Result<(), Box<dyn Error>> {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let (server_in_flight, server_max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+    let server = MockServer::start(move |_| {
+        let current = server_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        server_max_in_flight.fetch_max(current, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(50));
+        server_in_flight.fetch_sub(1, Ordering::SeqCst);
+        (
+            200,
+            json!({"object": "list", "results": [{"object": "block"}], "next_cursor": null})
+                .to_string(),
+        )
+    });
+    let mut client = AsyncNotionClient::with_limits("secret", 2, 0.0);
+    client.base_url = server.url.clone();
+
+    let block_ids = (0..8)
+        .map(|i| format!("8a281474f0714c548afc17d8a4b7c7{:02}", i))
+        .collect::<Vec<_>>();
+    let children = client.get_block_children_of_many(&block_ids).await?;
+    assert_eq!(children.len(), 8);
+    assert!(children.iter().all(|blocks| blocks.len() == 1));
+    assert_eq!(server.request_count(), 8);
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    // Ids are normalized as in NotionClient
+    assert!(server.requests().iter().any(|request| request.path
+        == "/v1/blocks/8a281474-f071-4c54-8afc-17d8a4b7c700/children?page_size=100"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_keeps_requests_per_second_budget() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|_| {
+        (
+            200,
+            json!({"object": "list", "results": [], "next_cursor": null}).to_string(),
+        )
+    });
+    let mut client = AsyncNotionClient::with_limits("secret", 5, 20.0);
+    client.base_url = server.url.clone();
+
+    let started = Instant::now();
+    let block_ids = (0..25)
+        .map(|i| format!("8a281474f0714c548afc17d8a4b7c7{:02}", i))
+        .collect::<Vec<_>>();
+    client.get_block_children_of_many(&block_ids).await?;
+    // The first 20 requests are sent in a burst, and the remaining 5 need 5 intervals of 50ms
+    assert!(started.elapsed() >= Duration::from_millis(250));
+
+    let metrics = client.rate_limiter.metrics();
+    assert_eq!(metrics.requests, 25);
+    assert_eq!(metrics.waited_requests, 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_paginates_property_items() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| {
+        let (results, next_cursor) = if request.path.contains("start_cursor=cursor1") {
+            (json!([{"object": "property_item", "id": "b"}]), json!(null))
+        } else {
+            (
+                json!([{"object": "property_item", "id": "a"}]),
+                json!("cursor1"),
+            )
+        };
+        (
+            200,
+            json!({
+                "object": "list",
+                "results": results,
+                "next_cursor": next_cursor,
+                "has_more": !next_cursor.is_null(),
+            })
+            .to_string(),
+        )
+    });
+    let mut client = AsyncNotionClient::with_limits("secret", 2, 0.0);
+    client.base_url = server.url.clone();
+
+    let items = client
+        .get_property_items_of_many(&[(
+            "8a281474f0714c548afc17d8a4b7c701".to_string(),
+            "prop1".to_string(),
+        )])
+        .await?;
+    let ids = items[0]
+        .iter()
+        .map(|item| item["id"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["a", "b"]);
+    assert_eq!(server.request_count(), 2);
+    assert!(server.requests()[0]
+        .path
+        .starts_with("/v1/pages/8a281474-f071-4c54-8afc-17d8a4b7c701/properties/prop1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_retries_rate_limited_requests() -> Result<(), Box<dyn Error>> {
+    let responses = AtomicUsize::new(0);
+    let server = MockServer::start(move |_| match responses.fetch_add(1, Ordering::SeqCst) {
+        0 => (
+            429,
+            r#"{"object":"error","status":429,"code":"rate_limited","message":"Rate limited"}"#
+                .to_string(),
+        ),
+        1 => (502, "Bad Gateway".to_string()),
+        _ => (
+            200,
+            json!({"object": "list", "results": [{"object": "block"}], "next_cursor": null})
+                .to_string(),
+        ),
+    });
+    let mut client = AsyncNotionClient::with_limits("secret", 2, 0.0);
+    client.base_url = server.url.clone();
+    client.retry_backoff = Duration::from_millis(1);
+
+    let blocks = client
+        .get_block_children("8a281474f0714c548afc17d8a4b7c701")
+        .await?;
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(server.request_count(), 3);
+    let metrics = client.rate_limiter.metrics();
+    assert_eq!((metrics.requests, metrics.retries), (3, 2));
+    Ok(())
+}
+
+#[test]
+fn it_exports_with_async_page_source() -> Result<(), Box<dyn Error>> {
+    let list_json = last_page_list_json();
+    let server = MockServer::start(move |request| {
+        if request.method == "GET" {
+            (200, fixtures::NOTION_DATABASE_JSON.to_string())
+        } else {
+            (200, list_json.clone())
+        }
+    });
+    let mut client = NotionClient::new("secret");
+    client.base_url = server.url.clone();
+    client.rate_limiter = Arc::new(RateLimiter::new(0.0));
+    let source = AsyncPageSource::new(AsyncNotionClient::from_client(&client, 2));
+
+    let output = "tmp/async_page_source.db";
+    before_db(output);
+    let summary = Exporter::builder()
+        .source(source)
+        .database_id("8a281474f0714c548afc17d8a4b7c781")
+        .sink(SqliteSink::new(output))
+        .build()?
+        .run()?;
+    assert_eq!(summary.tables[0].pages, 1);
+
+    let conn = rusqlite::Connection::open(output)?;
+    let count: i64 = conn.query_row("SELECT count(*) FROM pages", [], |row| row.get(0))?;
+    assert_eq!(count, 1);
+    // The async client shares the rate limiter of the blocking client
+    assert_eq!(client.rate_limiter.metrics().requests, 2);
+    Ok(())
+}
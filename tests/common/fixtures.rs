@@ -631,3 +631,285 @@ pub static NOTION_LIST_ALL_TYPES_JSON: &str = r#"
   "type": "page",
   "page": {}
 }"#;
+
+#[allow(dead_code)]
+pub static NOTION_DATABASE_NEW_TYPES_JSON: &str = r#"
+{
+  "object": "database",
+  "id": "3c1a5b2e-6d0f-4a8b-9e7c-2f4d6b8a0c1e",
+  "created_time": "2024-05-01T09:00:00.000Z",
+  "last_edited_time": "2024-05-02T10:00:00.000Z",
+  "title": [
+    {
+      "type": "text",
+      "text": {
+        "content": "New types",
+        "link": null
+      },
+      "plain_text": "New types",
+      "href": null
+    }
+  ],
+  "properties": {
+    "Name": {
+      "id": "title",
+      "name": "Name",
+      "type": "title",
+      "title": {}
+    },
+    "Status": {
+      "id": "kVMd",
+      "name": "Status",
+      "type": "status",
+      "status": {
+        "options": [
+          {
+            "id": "0b1c2d3e",
+            "name": "Not started",
+            "color": "default"
+          },
+          {
+            "id": "4f5a6b7c",
+            "name": "In progress",
+            "color": "blue"
+          },
+          {
+            "id": "8d9e0f1a",
+            "name": "Done",
+            "color": "green"
+          }
+        ],
+        "groups": [
+          {
+            "id": "g1",
+            "name": "To-do",
+            "color": "gray",
+            "option_ids": ["0b1c2d3e"]
+          },
+          {
+            "id": "g2",
+            "name": "In progress",
+            "color": "blue",
+            "option_ids": ["4f5a6b7c"]
+          },
+          {
+            "id": "g3",
+            "name": "Complete",
+            "color": "green",
+            "option_ids": ["8d9e0f1a"]
+          }
+        ]
+      }
+    },
+    "ID": {
+      "id": "%3DhCo",
+      "name": "ID",
+      "type": "unique_id",
+      "unique_id": {
+        "prefix": "TASK"
+      }
+    },
+    "Verification": {
+      "id": "Vrfy",
+      "name": "Verification",
+      "type": "verification",
+      "verification": {}
+    },
+    "Button": {
+      "id": "Btn1",
+      "name": "Button",
+      "type": "button",
+      "button": {}
+    },
+    "Place": {
+      "id": "Plc1",
+      "name": "Place",
+      "type": "place",
+      "place": {}
+    }
+  },
+  "parent": {
+    "type": "page_id",
+    "page_id": "5256af6e-80cc-4c63-a6f2-6fc9e4166239"
+  },
+  "url": "https://www.notion.so/3c1a5b2e6d0f4a8b9e7c2f4d6b8a0c1e",
+  "archived": false
+}
+"#;
+
+#[allow(dead_code)]
+pub static NOTION_LIST_NEW_TYPES_JSON: &str = r#"
+{
+  "object": "list",
+  "results": [
+    {
+      "object": "page",
+      "id": "7e2b9c4d-1a3f-4e5b-8c6d-0f1e2a3b4c5d",
+      "created_time": "2024-05-01T09:00:00.000Z",
+      "last_edited_time": "2024-05-02T10:00:00.000Z",
+      "created_by": {
+        "object": "user",
+        "id": "9d069f8b-6223-4853-b7eb-8fe3dfe7d389"
+      },
+      "last_edited_by": {
+        "object": "user",
+        "id": "9d069f8b-6223-4853-b7eb-8fe3dfe7d389"
+      },
+      "parent": {
+        "type": "database_id",
+        "database_id": "3c1a5b2e-6d0f-4a8b-9e7c-2f4d6b8a0c1e"
+      },
+      "archived": false,
+      "properties": {
+        "Name": {
+          "id": "title",
+          "type": "title",
+          "title": [
+            {
+              "type": "text",
+              "text": {
+                "content": "Write docs",
+                "link": null
+              },
+              "plain_text": "Write docs",
+              "href": null
+            }
+          ]
+        },
+        "Status": {
+          "id": "kVMd",
+          "type": "status",
+          "status": {
+            "id": "8d9e0f1a",
+            "name": "Done",
+            "color": "green"
+          }
+        },
+        "ID": {
+          "id": "%3DhCo",
+          "type": "unique_id",
+          "unique_id": {
+            "prefix": "TASK",
+            "number": 42
+          }
+        },
+        "Verification": {
+          "id": "Vrfy",
+          "type": "verification",
+          "verification": {
+            "state": "verified",
+            "verified_by": {
+              "object": "user",
+              "id": "9d069f8b-6223-4853-b7eb-8fe3dfe7d389"
+            },
+            "date": {
+              "start": "2024-05-02T00:00:00.000Z",
+              "end": null,
+              "time_zone": null
+            }
+          }
+        },
+        "Button": {
+          "id": "Btn1",
+          "type": "button",
+          "button": {}
+        },
+        "Place": {
+          "id": "Plc1",
+          "type": "place",
+          "place": {
+            "lat": 35.6812,
+            "lon": 139.7671,
+            "name": "Tokyo Station",
+            "address": "1 Chome Marunouchi, Chiyoda City, Tokyo",
+            "google_place_id": null
+          }
+        }
+      },
+      "url": "https://www.notion.so/Write-docs-7e2b9c4d1a3f4e5b8c6d0f1e2a3b4c5d"
+    }
+  ],
+  "next_cursor": null,
+  "has_more": false,
+  "type": "page",
+  "page": {}
+}
+"#;
+
+#[allow(dead_code)]
+pub static NOTION_DATABASE_WITH_DATA_SOURCES_JSON: &str = r#"
+{
+  "object": "database",
+  "id": "248104cd-477e-80fd-b757-e945d38000bd",
+  "title": [
+    {
+      "type": "text",
+      "text": {
+        "content": "Projects",
+        "link": null
+      },
+      "plain_text": "Projects",
+      "href": null
+    }
+  ],
+  "data_sources": [
+    {
+      "id": "248104cd-477e-80af-bc30-000bd28de8f9",
+      "name": "Tasks"
+    },
+    {
+      "id": "248104cd-477e-8096-9b38-000b1a2ad7a2",
+      "name": "Milestones"
+    }
+  ],
+  "parent": {
+    "type": "page_id",
+    "page_id": "5256af6e-80cc-4c63-a6f2-6fc9e4166239"
+  },
+  "is_inline": false,
+  "in_trash": false
+}
+"#;
+
+#[allow(dead_code)]
+pub static NOTION_DATA_SOURCE_JSON: &str = r#"
+{
+  "object": "data_source",
+  "id": "248104cd-477e-80af-bc30-000bd28de8f9",
+  "title": [
+    {
+      "type": "text",
+      "text": {
+        "content": "Tasks",
+        "link": null
+      },
+      "plain_text": "Tasks",
+      "href": null
+    }
+  ],
+  "properties": {
+    "Name": {
+      "id": "title",
+      "name": "Name",
+      "type": "title",
+      "title": {}
+    },
+    "Estimate": {
+      "id": "Est1",
+      "name": "Estimate",
+      "type": "number",
+      "number": {
+        "format": "number"
+      }
+    }
+  },
+  "parent": {
+    "type": "database_id",
+    "database_id": "248104cd-477e-80fd-b757-e945d38000bd"
+  },
+  "database_parent": {
+    "type": "page_id",
+    "page_id": "5256af6e-80cc-4c63-a6f2-6fc9e4166239"
+  }
+}
+"#;
@@ -5,3 +5,9 @@ pub fn before_db(database_path: &str) {
     fs::remove_file(database_path).ok();
     fs::create_dir("tmp").ok();
 }
+
+#[allow(dead_code)]
+pub fn before_dir(directory_path: &str) {
+    fs::remove_dir_all(directory_path).ok();
+    fs::create_dir("tmp").ok();
+}
@@ -1,2 +1,3 @@
 pub mod fixtures;
 pub mod helpers;
+pub mod mock_server;
@@ -0,0 +1,113 @@
+#![cfg(feature = "duckdb")]
+
+mod common;
+
+extern crate notion_into_sqlite;
+
+use std::error::Error;
+
+use duckdb::Connection;
+use serde_json::Value;
+
+use common::{fixtures, helpers};
+use notion_into_sqlite::diagnostics::ParseDiagnostics;
+use notion_into_sqlite::duckdb_sink::DuckDbSink;
+use notion_into_sqlite::exporter::Sink;
+use notion_into_sqlite::notion_database::parse_database_schema;
+use notion_into_sqlite::notion_pages::{
+    parse_notion_page_list_with_diagnostics, NotionPropertyValue,
+};
+
+#[test]
+fn it_writes_native_types_into_duckdb() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/duckdb1.duckdb";
+    helpers::before_db(database_path);
+
+    let schema = parse_database_schema(&serde_json::from_str::<Value>(
+        fixtures::NOTION_DATABASE_ALL_TYPES_JSON,
+    )?)?;
+    let list = serde_json::from_str::<Value>(fixtures::NOTION_LIST_ALL_TYPES_JSON)?;
+    let mut diagnostics = ParseDiagnostics::new();
+    let (mut pages, _) = parse_notion_page_list_with_diagnostics(&schema, &list, &mut diagnostics)?;
+    // Values are bound, so quotes in them are kept as they are
+    pages[0].properties.insert(
+        "MultiSelect".to_string(),
+        NotionPropertyValue::Json(serde_json::json!([{"name": "multi"}, {"name": "it's"}])),
+    );
+
+    let mut sink = DuckDbSink::new(database_path);
+    // Writing twice overwrites the rows
+    for _ in 0..2 {
+        sink.create_table(&schema, "pages")?;
+        sink.write_pages(&schema, "pages", &pages)?;
+    }
+    sink.finish()?;
+
+    let conn = Connection::open(database_path)?;
+    let count: i64 = conn.query_row("SELECT count(*) FROM pages", [], |row| row.get(0))?;
+    assert_eq!(count, 1);
+
+    let types: String = conn.query_row(
+        r#"SELECT concat_ws(',', typeof("Number"), typeof("Checkbox"), typeof("CreatedTime"), typeof("MultiSelect")) FROM pages"#,
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(types, "DOUBLE,BOOLEAN,TIMESTAMP,VARCHAR[]");
+    let date_type: String =
+        conn.query_row(r#"SELECT typeof("Date") FROM pages"#, [], |row| row.get(0))?;
+    assert!(date_type.starts_with("STRUCT("), "{}", date_type);
+
+    let (multi_select, date_start, created_time): (String, String, String) = conn.query_row(
+        r#"SELECT array_to_string("MultiSelect", ','), strftime("Date"."start", '%Y-%m-%d %H:%M'), strftime("CreatedTime", '%Y-%m-%d %H:%M') FROM pages"#,
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(multi_select, "multi,it's");
+    assert_eq!(date_start, "2022-03-20 00:00");
+    assert_eq!(created_time, "2022-03-21 02:39");
+
+    let archived: bool =
+        conn.query_row("SELECT archived FROM page_metadata", [], |row| row.get(0))?;
+    assert!(!archived);
+    Ok(())
+}
+
+#[test]
+fn it_adds_columns_for_new_properties_into_duckdb() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/duckdb3.duckdb";
+    helpers::before_db(database_path);
+
+    let database_json = serde_json::from_str::<Value>(fixtures::NOTION_DATABASE_ALL_TYPES_JSON)?;
+    let schema = parse_database_schema(&database_json)?;
+    let mut old_schema = parse_database_schema(&database_json)?;
+    old_schema.properties.remove("Number");
+    let list = serde_json::from_str::<Value>(fixtures::NOTION_LIST_ALL_TYPES_JSON)?;
+    let mut diagnostics = ParseDiagnostics::new();
+    let (pages, _) = parse_notion_page_list_with_diagnostics(&schema, &list, &mut diagnostics)?;
+
+    let mut sink = DuckDbSink::new(database_path);
+    sink.create_table(&old_schema, "pages")?;
+    sink.create_table(&schema, "pages")?;
+    sink.write_pages(&schema, "pages", &pages)?;
+    sink.finish()?;
+
+    let conn = Connection::open(database_path)?;
+    let number_type: String =
+        conn.query_row(r#"SELECT typeof("Number") FROM pages"#, [], |row| {
+            row.get(0)
+        })?;
+    assert_eq!(number_type, "DOUBLE");
+    Ok(())
+}
+
+#[test]
+fn it_rejects_existing_duckdb_file() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/duckdb2.duckdb";
+    helpers::before_db(database_path);
+
+    assert!(DuckDbSink::validate_database_path(database_path).is_ok());
+    std::fs::write(database_path, "")?;
+    let err = DuckDbSink::validate_database_path(database_path).unwrap_err();
+    assert_eq!(err.exit_code(), 6);
+    Ok(())
+}
@@ -0,0 +1,666 @@
+mod common;
+
+extern crate notion_into_sqlite;
+
+use rusqlite::Connection;
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use common::{fixtures, helpers};
+use notion_into_sqlite::diagnostics::ParseDiagnostics;
+use notion_into_sqlite::error::Result;
+use notion_into_sqlite::exporter::{Exporter, PageSource, Sink, TableSummary};
+use notion_into_sqlite::file_sink::FileSink;
+use notion_into_sqlite::notion_comments::NotionComment;
+use notion_into_sqlite::notion_database::{parse_database_schema, NotionDatabaseSchema};
+use notion_into_sqlite::notion_pages::{parse_notion_page_list_with_diagnostics, NotionPage};
+use notion_into_sqlite::rate_limiter::RateLimiter;
+use notion_into_sqlite::sqlite::{properties_table_names, SqliteSink};
+
+/// Comment on the page
+fn comment(page_id: &str) -> NotionComment {
+    NotionComment {
+        id: format!("{}-comment", page_id),
+        page_id: page_id.to_string(),
+        block_id: None,
+        discussion_id: "discussion".to_string(),
+        author_id: Some("user".to_string()),
+        created_time: "2022-07-15T16:52:00.000Z".to_string(),
+        rich_text: serde_json::json!([{"type": "text", "plain_text": "Looks good"}]),
+        plain_text: "Looks good".to_string(),
+    }
+}
+
+/// Page source which returns the fixtures instead of calling Notion API
+struct FixtureSource {
+    database_json: &'static str,
+    list_json: &'static str,
+}
+impl PageSource for FixtureSource {
+    fn get_database_schemas(&self, _database_id: &str) -> Result<Vec<NotionDatabaseSchema>> {
+        let json = serde_json::from_str::<serde_json::Value>(self.database_json).unwrap();
+        Ok(vec![parse_database_schema(&json)?])
+    }
+
+    fn get_all_pages(
+        &self,
+        _database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        let json = serde_json::from_str::<serde_json::Value>(self.list_json).unwrap();
+        let (pages, _) = parse_notion_page_list_with_diagnostics(schema, &json, diagnostics)?;
+        Ok(pages)
+    }
+
+    fn get_comments(&self, page_id: &str, include_blocks: bool) -> Result<Vec<NotionComment>> {
+        let comment = comment(page_id);
+        let mut block_comment = comment.clone();
+        block_comment.id = format!("{}-block-comment", page_id);
+        block_comment.block_id = Some("block".to_string());
+        if include_blocks {
+            Ok(vec![comment, block_comment])
+        } else {
+            Ok(vec![comment])
+        }
+    }
+}
+
+/// Page source which returns copies of the fixture page one per batch, failing at the given cursor
+#[derive(Default)]
+struct PagedSource {
+    fail_at: Option<&'static str>,
+    /// Index of the page which is returned without properties, so that it's skipped while parsing
+    unparseable: Option<usize>,
+    cursors: Rc<RefCell<Vec<Option<String>>>>,
+}
+impl PagedSource {
+    const PAGES: usize = 3;
+
+    fn page(
+        &self,
+        schema: &NotionDatabaseSchema,
+        index: usize,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        let mut json =
+            serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_JSON).unwrap();
+        json["results"][0]["id"] = serde_json::json!(format!("page-{}", index));
+        if self.unparseable == Some(index) {
+            json["results"][0]["properties"].take();
+        }
+        let (pages, _) = parse_notion_page_list_with_diagnostics(schema, &json, diagnostics)?;
+        Ok(pages)
+    }
+}
+impl PageSource for PagedSource {
+    fn get_database_schemas(&self, _database_id: &str) -> Result<Vec<NotionDatabaseSchema>> {
+        let json =
+            serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON).unwrap();
+        Ok(vec![parse_database_schema(&json)?])
+    }
+
+    fn get_all_pages(
+        &self,
+        _database_id: &str,
+        schema: &NotionDatabaseSchema,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<Vec<NotionPage>> {
+        let mut pages = vec![];
+        for index in 0..Self::PAGES {
+            pages.extend(self.page(schema, index, diagnostics)?);
+        }
+        Ok(pages)
+    }
+
+    fn get_pages(
+        &self,
+        _database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> Result<(Vec<NotionPage>, Option<String>)> {
+        self.cursors
+            .borrow_mut()
+            .push(start_cursor.map(|cursor| cursor.to_string()));
+        if start_cursor.is_some() && start_cursor == self.fail_at {
+            return Err(notion_into_sqlite::error::Error::InvalidArgument(
+                "interrupted".to_string(),
+            ));
+        }
+        let index = start_cursor.map_or(0, |cursor| cursor.parse().unwrap());
+        let next_cursor = Some(index + 1)
+            .filter(|next| *next < Self::PAGES)
+            .map(|next| next.to_string());
+        Ok((self.page(schema, index, diagnostics)?, next_cursor))
+    }
+
+    fn get_comments(&self, page_id: &str, _include_blocks: bool) -> Result<Vec<NotionComment>> {
+        Ok(vec![comment(page_id)])
+    }
+}
+
+/// Sink which records page ids written into each table
+#[derive(Clone, Default)]
+struct RecordingSink {
+    written: Rc<RefCell<Vec<(String, String)>>>,
+}
+impl Sink for RecordingSink {
+    fn create_table(&mut self, _schema: &NotionDatabaseSchema, _table_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_pages(
+        &mut self,
+        _schema: &NotionDatabaseSchema,
+        table_name: &str,
+        pages: &[NotionPage],
+    ) -> Result<()> {
+        for page in pages {
+            self.written
+                .borrow_mut()
+                .push((table_name.to_string(), page.id.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn it_exports_into_custom_sink() -> std::result::Result<(), Box<dyn Error>> {
+    let sink = RecordingSink::default();
+    let mut exporter = Exporter::builder()
+        .source(FixtureSource {
+            database_json: fixtures::NOTION_DATABASE_JSON,
+            list_json: fixtures::NOTION_LIST_JSON,
+        })
+        .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+        .sink(sink.clone())
+        .build()?;
+    let summary = exporter.run()?;
+    assert_eq!(
+        summary.tables,
+        vec![TableSummary {
+            database_id: "f2bf4cd7-b8d1-44fc-856e-8fe60c128b58".to_string(),
+            table_name: "pages".to_string(),
+            pages: 1,
+        }]
+    );
+    assert!(summary.diagnostics.is_empty());
+    assert_eq!(
+        sink.written.borrow().as_slice(),
+        &[(
+            "pages".to_string(),
+            "a75b9220-455d-48e1-a36b-c581a345f777".to_string()
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn it_exports_multiple_databases_repeatedly() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter1.db";
+    helpers::before_db(database_path);
+
+    let mut exporter = Exporter::builder()
+        .source(FixtureSource {
+            database_json: fixtures::NOTION_DATABASE_ALL_TYPES_JSON,
+            list_json: fixtures::NOTION_LIST_ALL_TYPES_JSON,
+        })
+        .database_id("8a281474-f071-4c54-8afc-17d8a4b7c782")
+        .database_id("8a281474-f071-4c54-8afc-17d8a4b7c782")
+        .sink(SqliteSink::new(database_path))
+        .build()?;
+    exporter.run()?;
+    let summary = exporter.run()?;
+    let table_names = summary
+        .tables
+        .iter()
+        .map(|table| table.table_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        table_names,
+        vec![
+            "pages_all_types",
+            "pages_all_types_8a281474_f071_4c54_8afc_17d8a4b7c782"
+        ]
+    );
+
+    let conn = Connection::open(database_path)?;
+    let count: i64 =
+        conn.query_row("SELECT count(*) FROM pages_all_types", [], |row| row.get(0))?;
+    assert_eq!(count, 1);
+
+    let (count, parent_type, parent_id): (i64, String, String) = conn.query_row(
+        "SELECT count(*), parent_type, parent_id FROM databases WHERE database_id = ?1",
+        ["8a281474-f071-4c54-8afc-17d8a4b7c782"],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(count, 2);
+    assert_eq!(parent_type, "page_id");
+    assert_eq!(parent_id, "5256af6e-80cc-4c63-a6f2-6fc9e4166239");
+    Ok(())
+}
+
+#[test]
+fn it_exports_comments() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter2.db";
+    helpers::before_db(database_path);
+
+    let source = || FixtureSource {
+        database_json: fixtures::NOTION_DATABASE_JSON,
+        list_json: fixtures::NOTION_LIST_JSON,
+    };
+    let summary = Exporter::builder()
+        .source(source())
+        .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+        .sink(SqliteSink::new(database_path))
+        .build()?
+        .run()?;
+    assert_eq!(summary.comments, 0);
+    let conn = Connection::open(database_path)?;
+    let tables: i64 = conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE name = 'comments'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(tables, 0);
+
+    let summary = Exporter::builder()
+        .source(source())
+        .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+        .sink(SqliteSink::new(database_path))
+        .comments(true)
+        .build()?
+        .run()?;
+    assert_eq!(summary.comments, 2);
+    let (count, plain_text, rich_text): (i64, String, String) = conn.query_row(
+        "SELECT count(*), plain_text, rich_text FROM comments WHERE page_id = ?1",
+        ["a75b9220-455d-48e1-a36b-c581a345f777"],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(count, 2);
+    assert_eq!(plain_text, "Looks good");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&rich_text)?[0]["plain_text"],
+        "Looks good"
+    );
+    Ok(())
+}
+
+#[test]
+fn it_reports_changes_in_dry_run() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter3.db";
+    helpers::before_db(database_path);
+
+    let exporter = |dry_run: bool| {
+        Exporter::builder()
+            .source(FixtureSource {
+                database_json: fixtures::NOTION_DATABASE_JSON,
+                list_json: fixtures::NOTION_LIST_JSON,
+            })
+            .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+            .sink(SqliteSink::new(database_path))
+            .dry_run(dry_run)
+            .build()
+    };
+
+    // Nothing is written in dry-run mode
+    let summary = exporter(true)?.run()?;
+    assert_eq!(summary.diffs.len(), 1);
+    assert!(summary.diffs[0].new_table);
+    assert_eq!(summary.diffs[0].inserted.len(), 1);
+    assert!(!std::path::Path::new(database_path).exists());
+
+    exporter(false)?.run()?;
+    let summary = exporter(true)?.run()?;
+    assert!(!summary.diffs[0].has_changes());
+    assert_eq!(summary.diffs[0].unchanged, 1);
+
+    let conn = Connection::open(database_path)?;
+    conn.execute(
+        "UPDATE page_metadata SET last_edited_time = '2022-01-01T00:00:00.000Z' WHERE id = ?1",
+        ["a75b9220-455d-48e1-a36b-c581a345f777"],
+    )?;
+    conn.execute("INSERT INTO pages (page_id) VALUES ('removed')", [])?;
+    conn.execute(r#"ALTER TABLE pages DROP COLUMN "Age""#, [])?;
+    let summary = exporter(true)?.run()?;
+    let diff = &summary.diffs[0];
+    assert!(diff.inserted.is_empty());
+    assert_eq!(diff.updated, vec!["a75b9220-455d-48e1-a36b-c581a345f777"]);
+    assert_eq!(diff.deleted, vec!["removed"]);
+    assert_eq!(diff.added_columns, vec!["Age"]);
+    let count: i64 = conn.query_row("SELECT count(*) FROM pages", [], |row| row.get(0))?;
+    assert_eq!(count, 2);
+
+    // The sync makes the changes which the dry run reported
+    let synced = exporter(false)?.run()?;
+    assert_eq!(synced.diffs, summary.diffs);
+    let (count, age): (i64, Option<f64>) =
+        conn.query_row(r#"SELECT count(*), max("Age") FROM pages"#, [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    assert_eq!((count, age), (1, Some(10.0)));
+    let summary = exporter(true)?.run()?;
+    assert!(!summary.diffs[0].has_changes());
+    Ok(())
+}
+
+#[test]
+fn it_records_sync_runs() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter4.db";
+    helpers::before_db(database_path);
+
+    // Requests sent before the run aren't counted
+    let rate_limiter = Arc::new(RateLimiter::new(0.0));
+    rate_limiter.acquire_blocking();
+    let exporter = |source: PagedSource| {
+        Exporter::builder()
+            .source(source)
+            .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+            .sink(SqliteSink::new(database_path))
+            .notion_version("2022-06-28")
+            .rate_limiter(rate_limiter.clone())
+            .build()
+    };
+    // Failed runs are recorded as well, even before anything else is written
+    let interrupted = PagedSource {
+        fail_at: Some("1"),
+        ..Default::default()
+    };
+    let err = exporter(interrupted)?
+        .run()
+        .map_err(|err| err.to_string())
+        .unwrap_err();
+    exporter(PagedSource::default())?.run()?;
+
+    let conn = Connection::open(database_path)?;
+    let (started_at, finished_at, fetched, inserted, error): (
+        String,
+        String,
+        i64,
+        Option<i64>,
+        String,
+    ) = conn.query_row(
+        "SELECT started_at, finished_at, pages_fetched, pages_inserted, error FROM sync_runs WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    )?;
+    assert!(started_at.ends_with('Z') && started_at <= finished_at);
+    assert_eq!((fetched, inserted), (0, None));
+    assert_eq!(error, err);
+
+    let (database_ids, notion_version, fetched, inserted, updated, requests, error): (
+        String,
+        String,
+        i64,
+        i64,
+        i64,
+        i64,
+        Option<String>,
+    ) = conn.query_row(
+        "SELECT database_ids, notion_version, pages_fetched, pages_inserted, pages_updated, requests, error FROM sync_runs WHERE id = 2",
+        [],
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+            ))
+        },
+    )?;
+    assert_eq!(database_ids, r#"["f2bf4cd7-b8d1-44fc-856e-8fe60c128b58"]"#);
+    assert_eq!(notion_version, "2022-06-28");
+    // The page written before the interruption is unchanged
+    assert_eq!((fetched, inserted, updated, requests), (3, 2, 0, 0));
+    assert_eq!(error, None);
+
+    // Nothing is recorded in dry-run mode
+    Exporter::builder()
+        .source(PagedSource::default())
+        .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+        .sink(SqliteSink::new(database_path))
+        .dry_run(true)
+        .build()?
+        .run()?;
+    let runs: i64 = conn.query_row("SELECT count(*) FROM sync_runs", [], |row| row.get(0))?;
+    assert_eq!(runs, 2);
+    Ok(())
+}
+
+#[test]
+fn it_resumes_from_checkpoint() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter5.db";
+    helpers::before_db(database_path);
+
+    let exporter = |source: PagedSource, resume: bool| {
+        Exporter::builder()
+            .source(source)
+            .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+            .sink(SqliteSink::new(database_path))
+            .checkpoints(resume)
+            .comments(false)
+            .build()
+    };
+    let count = |table: &str| -> rusqlite::Result<i64> {
+        Connection::open(database_path)?.query_row(
+            &format!("SELECT count(*) FROM {}", table),
+            [],
+            |row| row.get(0),
+        )
+    };
+    let read_checkpoint = || SqliteSink::new(database_path).read_checkpoint("pages");
+
+    // Batches written before the interruption are kept with their comments and the cursor of the next one
+    let interrupted = PagedSource {
+        fail_at: Some("2"),
+        ..Default::default()
+    };
+    assert!(exporter(interrupted, false)?.run().is_err());
+    assert_eq!(count("pages")?, 2);
+    assert_eq!(count("comments")?, 2);
+    let checkpoint = read_checkpoint()?.unwrap();
+    assert_eq!(checkpoint.next_cursor.as_deref(), Some("2"));
+    assert_eq!(checkpoint.pages, 2);
+    assert!(!checkpoint.completed);
+
+    let source = PagedSource::default();
+    let cursors = source.cursors.clone();
+    let summary = exporter(source, true)?.run()?;
+    assert_eq!(*cursors.borrow(), vec![Some("2".to_string())]);
+    assert_eq!(summary.tables[0].pages, 3);
+    assert_eq!(summary.comments, 1);
+    assert_eq!(summary.diffs[0].inserted, vec!["page-2"]);
+    assert!(summary.diffs[0].deleted.is_empty());
+    assert_eq!(count("pages")?, 3);
+    assert_eq!(count("comments")?, 3);
+    // Checkpoints are deleted once the export has completed
+    assert_eq!(read_checkpoint()?, None);
+
+    // Resuming a completed export fetches all pages again, so that deleted pages are told
+    let conn = Connection::open(database_path)?;
+    conn.execute("INSERT INTO pages (page_id) VALUES ('removed')", [])?;
+    let source = PagedSource::default();
+    let cursors = source.cursors.clone();
+    let summary = exporter(source, true)?.run()?;
+    assert_eq!(
+        *cursors.borrow(),
+        vec![None, Some("1".to_string()), Some("2".to_string())]
+    );
+    assert_eq!(summary.tables[0].pages, 3);
+    assert_eq!(summary.diffs[0].unchanged, 3);
+    assert_eq!(summary.diffs[0].deleted, vec!["removed"]);
+    assert_eq!(count("pages")?, 3);
+
+    // The checkpoint can't be used once the schema has changed
+    let interrupted = PagedSource {
+        fail_at: Some("1"),
+        ..Default::default()
+    };
+    assert!(exporter(interrupted, false)?.run().is_err());
+    conn.execute("UPDATE checkpoints SET schema = '[]'", [])?;
+    let err = exporter(PagedSource::default(), true)?.run().unwrap_err();
+    assert_eq!(err.exit_code(), 2);
+
+    // A checkpoint which can't be read isn't taken as having no query
+    conn.execute("UPDATE checkpoints SET query = '{'", [])?;
+    assert!(read_checkpoint().is_err());
+    Ok(())
+}
+
+#[test]
+fn it_resumes_only_the_latest_run() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter8.db";
+    helpers::before_db(database_path);
+
+    let database_ids = [
+        "f2bf4cd7-b8d1-44fc-856e-8fe60c128b58",
+        "f2bf4cd7-b8d1-44fc-856e-8fe60c128b59",
+    ];
+    let exporter = |source: PagedSource, resume: bool| {
+        Exporter::builder()
+            .source(source)
+            .database_id(database_ids[0])
+            .database_id(database_ids[1])
+            .sink(SqliteSink::new(database_path))
+            .checkpoints(resume)
+            .build()
+    };
+
+    // The run is interrupted in the first table
+    let interrupted = PagedSource {
+        fail_at: Some("2"),
+        ..Default::default()
+    };
+    assert!(exporter(interrupted, false)?.run().is_err());
+    // An earlier run left a checkpoint of the second table
+    let schemas = PagedSource::default().get_database_schemas(database_ids[0])?;
+    let table_names = properties_table_names([&schemas[0], &schemas[0]]);
+    let mut sink = SqliteSink::new(database_path);
+    let mut stale = sink.read_checkpoint(&table_names[0])?.unwrap();
+    stale.run_id -= 1;
+    stale.table_name = table_names[1].clone();
+    stale.database_id = database_ids[1].to_string();
+    sink.write_checkpoint(&stale)?;
+
+    // The first table continues from the checkpoint, and the second one starts over
+    let source = PagedSource::default();
+    let cursors = source.cursors.clone();
+    let summary = exporter(source, true)?.run()?;
+    assert_eq!(
+        *cursors.borrow(),
+        vec![
+            Some("2".to_string()),
+            None,
+            Some("1".to_string()),
+            Some("2".to_string())
+        ]
+    );
+    assert_eq!(summary.tables[1].pages, 3);
+    Ok(())
+}
+
+#[test]
+fn it_saves_no_checkpoints_unless_enabled() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter6.db";
+    helpers::before_db(database_path);
+
+    Exporter::builder()
+        .source(PagedSource::default())
+        .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+        .sink(SqliteSink::new(database_path))
+        .build()?
+        .run()?;
+    let conn = Connection::open(database_path)?;
+    let (pages, checkpoints): (i64, i64) = conn.query_row(
+        "SELECT (SELECT count(*) FROM pages),
+            (SELECT count(*) FROM sqlite_master WHERE name = 'checkpoints')",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((pages, checkpoints), (3, 0));
+    Ok(())
+}
+
+#[test]
+fn it_keeps_pages_which_fail_to_parse() -> std::result::Result<(), Box<dyn Error>> {
+    let database_path = "tmp/exporter7.db";
+    helpers::before_db(database_path);
+
+    let exporter = |source: PagedSource, dry_run: bool| {
+        Exporter::builder()
+            .source(source)
+            .database_id("f2bf4cd7-b8d1-44fc-856e-8fe60c128b58")
+            .sink(SqliteSink::new(database_path).history(true))
+            .dry_run(dry_run)
+            .build()
+    };
+    exporter(PagedSource::default(), false)?.run()?;
+
+    // The stored page isn't taken as deleted from Notion, whether pages are fetched at once or in batches
+    for dry_run in [true, false] {
+        let source = PagedSource {
+            unparseable: Some(1),
+            ..Default::default()
+        };
+        let summary = exporter(source, dry_run)?.run()?;
+        assert_eq!(summary.diagnostics.skipped_pages(), 1);
+        assert!(summary.diffs[0].deleted.is_empty());
+    }
+
+    let conn = Connection::open(database_path)?;
+    let (pages, open_history): (i64, i64) = conn.query_row(
+        "SELECT (SELECT count(*) FROM pages WHERE page_id = 'page-1'),
+            (SELECT count(*) FROM pages_history WHERE page_id = 'page-1' AND _valid_to IS NULL)",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((pages, open_history), (1, 1));
+    Ok(())
+}
+
+#[test]
+fn it_requires_source_sink_and_database_id() {
+    assert!(Exporter::builder()
+        .sink(RecordingSink::default())
+        .database_id("xxxx")
+        .build()
+        .is_err());
+    assert!(Exporter::builder()
+        .source(FixtureSource {
+            database_json: fixtures::NOTION_DATABASE_JSON,
+            list_json: fixtures::NOTION_LIST_JSON,
+        })
+        .sink(RecordingSink::default())
+        .build()
+        .is_err());
+    assert!(Exporter::builder()
+        .source(FixtureSource {
+            database_json: fixtures::NOTION_DATABASE_JSON,
+            list_json: fixtures::NOTION_LIST_JSON,
+        })
+        .sink(RecordingSink::default())
+        .database_id("xxxx")
+        .strict(true)
+        .checkpoints(false)
+        .build()
+        .is_err());
+    // Sinks which can't keep checkpoints would overwrite comments of earlier batches
+    let err = Exporter::builder()
+        .source(FixtureSource {
+            database_json: fixtures::NOTION_DATABASE_JSON,
+            list_json: fixtures::NOTION_LIST_JSON,
+        })
+        .sink(FileSink::csv("tmp/exporter9"))
+        .database_id("xxxx")
+        .checkpoints(false)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.exit_code(), 2);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+extern crate notion_into_sqlite;
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use common::{fixtures, helpers};
+use notion_into_sqlite::exporter::Sink;
+use notion_into_sqlite::file_sink::FileSink;
+use notion_into_sqlite::notion_database::{parse_database_schema, NotionDatabaseSchema};
+use notion_into_sqlite::notion_pages::{NotionPage, NotionPropertyValue};
+
+fn schema() -> NotionDatabaseSchema {
+    let json = serde_json::from_str::<Value>(fixtures::NOTION_DATABASE_JSON).unwrap();
+    parse_database_schema(&json).unwrap()
+}
+
+fn pages() -> Vec<NotionPage> {
+    let page = |id: &str, properties: Vec<(&str, NotionPropertyValue)>| NotionPage {
+        id: id.to_string(),
+        properties: properties
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<HashMap<_, _>>(),
+        url: format!("https://www.notion.so/{}", id),
+        created_time: "2022-03-12T00:15:00.000Z".to_string(),
+        created_by: json!({"object": "user", "id": "user1"}),
+        last_edited_time: "2022-03-12T00:16:00.000Z".to_string(),
+        last_edited_by: json!({"object": "user", "id": "user1"}),
+        archived: false,
+    };
+    vec![
+        page(
+            "xxxx",
+            vec![
+                (
+                    "Name",
+                    NotionPropertyValue::Text("Meu, \"the cat\"".to_string()),
+                ),
+                ("Age", NotionPropertyValue::Number(5.0)),
+                ("Animal", NotionPropertyValue::Text("cat".to_string())),
+            ],
+        ),
+        // Properties missing in the page are written as empty values
+        page(
+            "yyyy",
+            vec![("Name", NotionPropertyValue::Text("Multi\nline".to_string()))],
+        ),
+    ]
+}
+
+fn export(sink: &mut FileSink) -> Result<(), Box<dyn Error>> {
+    let schema = schema();
+    sink.create_table(&schema, "pages")?;
+    sink.write_pages(&schema, "pages", &pages())?;
+    sink.finish()?;
+    Ok(())
+}
+
+#[test]
+fn it_writes_csv_files() -> Result<(), Box<dyn Error>> {
+    let directory = "tmp/csv1";
+    helpers::before_dir(directory);
+
+    let mut sink = FileSink::csv(directory);
+    export(&mut sink)?;
+    // Running again overwrites the files
+    export(&mut sink)?;
+
+    let pages = fs::read_to_string(format!("{}/pages.csv", directory))?;
+    assert_eq!(
+        pages,
+        "page_id,Age,Animal,Name\nxxxx,5,cat,\"Meu, \"\"the cat\"\"\"\nyyyy,,,\"Multi\nline\"\n"
+    );
+
+    let metadata = fs::read_to_string(format!("{}/page_metadata.csv", directory))?;
+    let lines = metadata.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "id,url,created_time,created_by,last_edited_time,last_edited_by,archived"
+    );
+    assert!(lines[1].starts_with("xxxx,https://www.notion.so/xxxx,"));
+    assert!(lines[1].ends_with(",false"));
+    Ok(())
+}
+
+#[test]
+fn it_writes_jsonl_files() -> Result<(), Box<dyn Error>> {
+    let directory = "tmp/jsonl1";
+    helpers::before_dir(directory);
+
+    let mut sink = FileSink::jsonl(directory);
+    export(&mut sink)?;
+
+    let pages = fs::read_to_string(format!("{}/pages.jsonl", directory))?
+        .lines()
+        .map(serde_json::from_str::<Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        pages,
+        vec![
+            json!({"page_id": "xxxx", "Age": 5.0, "Animal": "cat", "Name": "Meu, \"the cat\""}),
+            json!({"page_id": "yyyy", "Age": null, "Animal": null, "Name": "Multi\nline"}),
+        ]
+    );
+
+    let metadata = fs::read_to_string(format!("{}/page_metadata.jsonl", directory))?;
+    let metadata = serde_json::from_str::<Value>(metadata.lines().next().unwrap())?;
+    assert_eq!(
+        metadata["created_by"],
+        json!({"object": "user", "id": "user1"})
+    );
+    assert_eq!(metadata["archived"], json!(false));
+    Ok(())
+}
+
+#[test]
+fn it_validates_directory_path() -> Result<(), Box<dyn Error>> {
+    let directory = "tmp/csv2";
+    helpers::before_dir(directory);
+
+    assert!(FileSink::validate_directory_path(directory).is_ok());
+    fs::create_dir(directory)?;
+    assert!(FileSink::validate_directory_path(directory).is_ok());
+    fs::write(format!("{}/pages.csv", directory), "")?;
+    let err = FileSink::validate_directory_path(directory).unwrap_err();
+    assert_eq!(err.exit_code(), 6);
+    Ok(())
+}
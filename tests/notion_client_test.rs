@@ -0,0 +1,490 @@
+mod common;
+
+extern crate notion_into_sqlite;
+
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::fixtures;
+use common::mock_server::{MockServer, RecordedRequest};
+use notion_into_sqlite::diagnostics::ParseDiagnostics;
+use notion_into_sqlite::notion_client::NotionClient;
+use notion_into_sqlite::notion_database::{parse_database_schema, NotionDatabaseSchema};
+use notion_into_sqlite::notion_pages::NotionPropertyValue;
+use notion_into_sqlite::query::QueryOptions;
+use notion_into_sqlite::rate_limiter::RateLimiter;
+use serde_json::{json, Value};
+
+static DATABASE_ID: &str = "8a281474f0714c548afc17d8a4b7c782";
+
+fn database_schema() -> NotionDatabaseSchema {
+    let json = serde_json::from_str::<Value>(fixtures::NOTION_DATABASE_JSON).unwrap();
+    parse_database_schema(&json).unwrap()
+}
+
+/// Client for the mock server without rate limit, which retries without waiting long
+fn mock_client(server: &MockServer) -> NotionClient {
+    let mut client = NotionClient::new("secret");
+    client.base_url = server.url.clone();
+    client.rate_limiter = Arc::new(RateLimiter::new(0.0));
+    client.retry_backoff = Duration::from_millis(1);
+    client
+}
+
+/// Id of the page like "8a281474-f071-4c54-8afc-000000000012"
+fn page_id(index: usize) -> String {
+    format!("8a281474-f071-4c54-8afc-{:012}", index)
+}
+
+/// Page of the fixture with the id of the index
+fn page_json(index: usize) -> Value {
+    let list = serde_json::from_str::<Value>(fixtures::NOTION_LIST_JSON).unwrap();
+    let mut page = list["results"][0].clone();
+    page["id"] = page_id(index).into();
+    page
+}
+
+/// Answer a query for a database of `total` pages, returning at most `max_results` pages per request.
+/// The cursor is the offset of the next page.
+fn query_response(request: &RecordedRequest, total: usize, max_results: usize) -> (u16, String) {
+    let body = serde_json::from_str::<Value>(&request.body).unwrap();
+    let page_size = body["page_size"].as_u64().unwrap() as usize;
+    let offset = body["start_cursor"]
+        .as_str()
+        .map(|cursor| cursor.parse::<usize>().unwrap())
+        .unwrap_or(0);
+    let end = (offset + page_size.min(max_results)).min(total);
+    let has_more = end < total;
+    let response = json!({
+        "object": "list",
+        "results": (offset..end).map(page_json).collect::<Vec<_>>(),
+        "next_cursor": if has_more { json!(end.to_string()) } else { json!(null) },
+        "has_more": has_more,
+    });
+    (200, response.to_string())
+}
+
+#[test]
+fn it_fetches_pages_with_default_page_size() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| query_response(request, 250, 100));
+    let client = mock_client(&server);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+    assert!(diagnostics.is_empty());
+    assert_eq!(pages.len(), 250);
+    assert_eq!(pages[0].id, page_id(0));
+    assert_eq!(pages[249].id, page_id(249));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|request| request.body.contains(r#""page_size":100"#)));
+    Ok(())
+}
+
+#[test]
+fn it_fetches_pages_with_custom_page_size() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| query_response(request, 25, 100));
+    let mut client = mock_client(&server);
+    client.page_size = 10;
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+    assert_eq!(pages.len(), 25);
+    assert_eq!(server.request_count(), 3);
+    Ok(())
+}
+
+#[test]
+fn it_keeps_fetching_after_partial_pages() -> Result<(), Box<dyn Error>> {
+    // Notion API may return fewer results than the page size even when it has more
+    let server = MockServer::start(|request| query_response(request, 30, 7));
+    let client = mock_client(&server);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+    assert_eq!(pages.len(), 30);
+    assert_eq!(server.request_count(), 5);
+    Ok(())
+}
+
+#[test]
+fn it_stops_when_has_more_is_false() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|_| {
+        let response = json!({
+            "object": "list",
+            "results": [page_json(0)],
+            "next_cursor": "stale-cursor",
+            "has_more": false,
+        });
+        (200, response.to_string())
+    });
+    let client = mock_client(&server);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(server.request_count(), 1);
+    Ok(())
+}
+
+#[test]
+fn it_retries_rate_limited_and_failed_requests() -> Result<(), Box<dyn Error>> {
+    let responses = AtomicUsize::new(0);
+    let server = MockServer::start(
+        move |request| match responses.fetch_add(1, Ordering::SeqCst) {
+            0 => (
+                429,
+                r#"{"object":"error","status":429,"code":"rate_limited","message":"Rate limited"}"#
+                    .to_string(),
+            ),
+            1 => (502, "Bad Gateway".to_string()),
+            _ => query_response(request, 1, 100),
+        },
+    );
+    let client = mock_client(&server);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(server.request_count(), 3);
+    // Retries have the same body as the first request
+    let requests = server.requests();
+    assert_eq!(requests[0].body, requests[2].body);
+    let metrics = client.rate_limiter.metrics();
+    assert_eq!((metrics.requests, metrics.retries), (3, 2));
+    Ok(())
+}
+
+#[test]
+fn it_gives_up_after_max_retries() {
+    let server = MockServer::start(|_| {
+        (
+            503,
+            r#"{"object":"error","status":503,"code":"service_unavailable","message":"Unavailable"}"#
+                .to_string(),
+        )
+    });
+    let mut client = mock_client(&server);
+    client.max_retries = 2;
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let result = client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics);
+    assert!(result.is_err());
+    assert_eq!(server.request_count(), 3);
+    assert_eq!(client.rate_limiter.metrics().retries, 2);
+}
+
+#[test]
+fn it_rejects_invalid_page_size() {
+    let mut client = NotionClient::new("secret");
+    client.base_url = "http://127.0.0.1:1/v1".into();
+    client.page_size = 101;
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let err = client
+        .get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)
+        .unwrap_err();
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[test]
+fn it_sends_filter_and_sorts() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| query_response(request, 1, 100));
+    let mut client = mock_client(&server);
+    client.query = QueryOptions {
+        filter: Some("Name = foo".parse()?),
+        sorts: vec!["Age:desc".parse()?],
+    };
+
+    let mut diagnostics = ParseDiagnostics::new();
+    client.get_all_pages(DATABASE_ID, &database_schema(), &mut diagnostics)?;
+
+    let body = serde_json::from_str::<Value>(&server.requests()[0].body)?;
+    assert_eq!(
+        body["filter"],
+        json!({"property": "Name", "title": {"equals": "foo"}})
+    );
+    assert_eq!(
+        body["sorts"],
+        json!([{"property": "Age", "direction": "descending"}])
+    );
+    Ok(())
+}
+
+#[test]
+fn it_fetches_full_values_of_truncated_properties() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| {
+        if request.method == "POST" {
+            let mut list =
+                serde_json::from_str::<Value>(fixtures::NOTION_LIST_ALL_TYPES_JSON).unwrap();
+            let relation = &mut list["results"][0]["properties"]["Relation"];
+            relation["relation"] = json!((0..25)
+                .map(|i| json!({ "id": format!("rel{}", i) }))
+                .collect::<Vec<_>>());
+            relation["has_more"] = true.into();
+            list["next_cursor"] = Value::Null;
+            list["has_more"] = false.into();
+            return (200, list.to_string());
+        }
+
+        // Property items are returned 20 at a time out of 30
+        let offset = if request.path.contains("start_cursor=20") {
+            20
+        } else {
+            0
+        };
+        let end = (offset + 20).min(30);
+        let response = json!({
+            "object": "list",
+            "results": (offset..end).map(|i| json!({
+                "object": "property_item",
+                "id": "knfs",
+                "type": "relation",
+                "relation": { "id": format!("rel{}", i) },
+            })).collect::<Vec<_>>(),
+            "next_cursor": if end < 30 { json!(end.to_string()) } else { json!(null) },
+            "has_more": end < 30,
+            "type": "property_item",
+        });
+        (200, response.to_string())
+    });
+    let client = mock_client(&server);
+    let schema = parse_database_schema(&serde_json::from_str::<Value>(
+        fixtures::NOTION_DATABASE_ALL_TYPES_JSON,
+    )?)?;
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let pages = client.get_all_pages(DATABASE_ID, &schema, &mut diagnostics)?;
+    let relation = match pages[0].properties.get("Relation") {
+        Some(NotionPropertyValue::Json(relation)) => relation.as_array().unwrap().clone(),
+        value => panic!("Unexpected value: {:?}", value),
+    };
+    assert_eq!(relation.len(), 30);
+    assert_eq!(relation[29], json!({"id": "rel29"}));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[1]
+        .path
+        .starts_with(&format!("/v1/pages/{}/properties/knfs", pages[0].id)));
+    Ok(())
+}
+
+/// Search response with databases of the titles
+fn search_response(titles: &[&str]) -> String {
+    let results = titles
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            json!({
+                "object": "database",
+                "id": format!("8a281474-f071-4c54-8afc-17d8a4b7c78{}", i),
+                "title": [{"plain_text": title}],
+                "parent": {"type": "workspace", "workspace": true},
+                "last_edited_time": "2022-03-12T00:16:00.000Z",
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({"object": "list", "results": results, "next_cursor": null, "has_more": false})
+        .to_string()
+}
+
+#[test]
+fn it_searches_databases() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|_| (200, search_response(&["Tasks", "Bugs"])));
+    let client = mock_client(&server);
+
+    let databases = client.search_databases(None)?;
+    assert_eq!(databases.len(), 2);
+    assert_eq!(databases[1].title, "Bugs");
+    assert_eq!(databases[1].parent, "workspace");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/search");
+    let body = serde_json::from_str::<Value>(&request.body)?;
+    assert_eq!(
+        body["filter"],
+        json!({"property": "object", "value": "database"})
+    );
+    Ok(())
+}
+
+#[test]
+fn it_resolves_database_id_from_title() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start(|request| {
+        if request.body.contains("Duplicated") {
+            (200, search_response(&["Duplicated", "duplicated"]))
+        } else {
+            (200, search_response(&["Tasks", "Tasks archive"]))
+        }
+    });
+    let client = mock_client(&server);
+
+    assert_eq!(
+        client.resolve_database_id("Tasks")?,
+        "8a281474-f071-4c54-8afc-17d8a4b7c780"
+    );
+    assert!(client.resolve_database_id("Unknown").is_err());
+    assert!(client.resolve_database_id("Duplicated").is_err());
+
+    // Ids and URLs are resolved without requests
+    let requests = server.request_count();
+    assert_eq!(
+        client.resolve_database_id(
+            "https://www.notion.so/workspace/Tasks-8a281474f0714c548afc17d8a4b7c782?v=1"
+        )?,
+        "8a281474-f071-4c54-8afc-17d8a4b7c782"
+    );
+    assert_eq!(server.request_count(), requests);
+    Ok(())
+}
+
+#[test]
+fn it_rejects_invalid_database_id_without_requests() {
+    let server = MockServer::start(|_| (200, fixtures::NOTION_DATABASE_JSON.to_string()));
+    let client = mock_client(&server);
+
+    let err = client
+        .get_database_schemas("8a281474f0714c548afc")
+        .unwrap_err();
+    assert_eq!(err.exit_code(), 2);
+    assert_eq!(server.request_count(), 0);
+}
+
+#[test]
+fn it_reports_page_id_given_as_database_id() {
+    let server = MockServer::start(|request| {
+        if request.path.starts_with("/v1/databases/") {
+            let error = json!({
+                "object": "error",
+                "status": 404,
+                "code": "object_not_found",
+                "message": "Could not find database with ID: 8a281474-f071-4c54-8afc-17d8a4b7c782.",
+            });
+            (404, error.to_string())
+        } else {
+            (200, page_json(0).to_string())
+        }
+    });
+    let client = mock_client(&server);
+
+    let err = client
+        .get_database_schemas(
+            "https://www.notion.so/workspace/Page-8a281474f0714c548afc17d8a4b7c782",
+        )
+        .unwrap_err();
+    assert_eq!(err.exit_code(), 2);
+    assert!(err.to_string().contains("is a page, not a database"));
+    let paths = server
+        .requests()
+        .iter()
+        .map(|request| request.path.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "/v1/databases/8a281474-f071-4c54-8afc-17d8a4b7c782",
+            "/v1/pages/8a281474-f071-4c54-8afc-17d8a4b7c782",
+        ]
+    );
+}
+
+/// Comment on the block with the given index
+fn comment_json(block_id: &str, index: usize) -> Value {
+    json!({
+        "object": "comment",
+        "id": format!("{}-comment{}", block_id, index),
+        "parent": {"type": "block_id", "block_id": block_id},
+        "discussion_id": format!("{}-discussion", block_id),
+        "created_time": "2022-07-15T16:52:00.000Z",
+        "created_by": {"object": "user", "id": "user1"},
+        "rich_text": [{"type": "text", "plain_text": format!("Comment {}", index)}],
+    })
+}
+
+#[test]
+fn it_fetches_comments_of_page_and_blocks() -> Result<(), Box<dyn Error>> {
+    let page_id = "8a281474-f071-4c54-8afc-17d8a4b7c782";
+    let toggle_id = "00000000-0000-4000-8000-000000000001";
+    let paragraph_id = "00000000-0000-4000-8000-000000000002";
+    let child_page_id = "00000000-0000-4000-8000-000000000003";
+    let server = MockServer::start(move |request| {
+        let list = |results: Vec<Value>, next_cursor: Option<&str>| {
+            let response = json!({
+                "object": "list",
+                "results": results,
+                "next_cursor": next_cursor,
+                "has_more": next_cursor.is_some(),
+            });
+            (200, response.to_string())
+        };
+        if request.path.starts_with("/v1/comments") {
+            // The page has 3 comments returned in 2 responses, and the paragraph has 1
+            if request.path.contains(page_id) && request.path.contains("start_cursor=2") {
+                list(vec![comment_json(page_id, 2)], None)
+            } else if request.path.contains(page_id) {
+                let comments = vec![comment_json(page_id, 0), comment_json(page_id, 1)];
+                list(comments, Some("2"))
+            } else if request.path.contains(paragraph_id) {
+                list(vec![comment_json(paragraph_id, 0)], None)
+            } else {
+                list(vec![], None)
+            }
+        } else if request
+            .path
+            .starts_with(&format!("/v1/blocks/{}/", page_id))
+        {
+            let blocks = vec![
+                json!({"object": "block", "id": toggle_id, "type": "toggle", "has_children": true}),
+                json!({"object": "block", "id": child_page_id, "type": "child_page", "has_children": true}),
+            ];
+            list(blocks, None)
+        } else if request
+            .path
+            .starts_with(&format!("/v1/blocks/{}/", toggle_id))
+        {
+            // Block ids are normalized before requesting their comments
+            let blocks = vec![
+                json!({"object": "block", "id": paragraph_id.replace('-', ""), "type": "paragraph", "has_children": false}),
+            ];
+            list(blocks, None)
+        } else {
+            (404, json!({"object": "error", "status": 404}).to_string())
+        }
+    });
+    let client = mock_client(&server);
+
+    let comments = client.get_comments(page_id, false)?;
+    assert_eq!(comments.len(), 3);
+    assert_eq!(comments[2].plain_text, "Comment 2");
+    assert_eq!(server.request_count(), 2);
+
+    let comments = client.get_comments(page_id, true)?;
+    let ids = comments
+        .iter()
+        .map(|comment| comment.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        vec![
+            format!("{}-comment0", page_id),
+            format!("{}-comment1", page_id),
+            format!("{}-comment2", page_id),
+            format!("{}-comment0", paragraph_id),
+        ]
+    );
+    assert!(comments.iter().all(|comment| comment.page_id == page_id));
+    assert_eq!(comments[3].block_id, Some(paragraph_id.to_string()));
+
+    // Comments of the child page are not fetched
+    assert!(!server
+        .requests()
+        .iter()
+        .any(|request| request.path.contains(child_page_id)));
+    Ok(())
+}
@@ -3,13 +3,16 @@ mod common;
 extern crate notion_into_sqlite;
 
 use common::fixtures;
-use notion_into_sqlite::notion_database::{parse_database_schema, NotionPropertyType};
+use notion_into_sqlite::notion_database::{
+    parse_data_sources, parse_database_schema, NotionDataSource, NotionParent, NotionPropertyType,
+};
 use std::error::Error;
 
 #[test]
 fn it_parses_database_json() -> Result<(), Box<dyn Error>> {
     let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON)?;
     let schema = parse_database_schema(&json)?;
+    assert!(schema.data_source.is_none());
     let properties = schema.properties;
     assert_eq!(properties.len(), 3);
 
@@ -34,8 +37,98 @@ fn it_parses_database_json() -> Result<(), Box<dyn Error>> {
 fn it_parses_database_json_with_all_property_types() -> Result<(), Box<dyn Error>> {
     let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_ALL_TYPES_JSON)?;
     let schema = parse_database_schema(&json)?;
+    assert_eq!(
+        schema.parent,
+        NotionParent {
+            parent_type: "page_id".to_string(),
+            id: Some("5256af6e-80cc-4c63-a6f2-6fc9e4166239".to_string()),
+        }
+    );
     let properties = schema.properties;
     assert_eq!(properties.len(), 19);
 
     Ok(())
 }
+
+#[test]
+fn it_parses_database_json_with_new_property_types() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_NEW_TYPES_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let properties = schema.properties;
+    assert_eq!(properties.len(), 6);
+
+    let status_property = properties.get("Status").unwrap();
+    assert_eq!(status_property.property_type, NotionPropertyType::Status);
+    assert_eq!(
+        status_property.status_groups.get("Done").unwrap(),
+        "Complete"
+    );
+    assert_eq!(status_property.status_groups.len(), 3);
+
+    assert_eq!(
+        properties.get("ID").unwrap().property_type,
+        NotionPropertyType::UniqueId
+    );
+    assert_eq!(
+        properties.get("Verification").unwrap().property_type,
+        NotionPropertyType::Verification
+    );
+    assert_eq!(
+        properties.get("Button").unwrap().property_type,
+        NotionPropertyType::Button
+    );
+    assert_eq!(
+        properties.get("Place").unwrap().property_type,
+        NotionPropertyType::Place
+    );
+    Ok(())
+}
+
+#[test]
+fn it_parses_data_sources_of_database() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(
+        fixtures::NOTION_DATABASE_WITH_DATA_SOURCES_JSON,
+    )?;
+    let data_sources = parse_data_sources(&json)?;
+    assert_eq!(
+        data_sources,
+        vec![
+            NotionDataSource {
+                id: "248104cd-477e-80af-bc30-000bd28de8f9".to_string(),
+                name: "Tasks".to_string(),
+            },
+            NotionDataSource {
+                id: "248104cd-477e-8096-9b38-000b1a2ad7a2".to_string(),
+                name: "Milestones".to_string(),
+            },
+        ]
+    );
+
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATA_SOURCE_JSON)?;
+    assert!(parse_data_sources(&json).is_err());
+    Ok(())
+}
+
+#[test]
+fn it_parses_data_source_json() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATA_SOURCE_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    assert_eq!(
+        schema.data_source,
+        Some(NotionDataSource {
+            id: "248104cd-477e-80af-bc30-000bd28de8f9".to_string(),
+            name: "Tasks".to_string(),
+        })
+    );
+    // Parent of the database which the data source belongs to
+    assert_eq!(
+        schema.parent.to_string(),
+        "page_id:5256af6e-80cc-4c63-a6f2-6fc9e4166239"
+    );
+    assert_eq!(schema.properties.len(), 2);
+    assert_eq!(
+        schema.properties.get("Estimate").unwrap().property_type,
+        NotionPropertyType::Number
+    );
+    Ok(())
+}
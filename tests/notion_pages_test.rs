@@ -3,8 +3,11 @@ mod common;
 extern crate notion_into_sqlite;
 
 use common::fixtures;
+use notion_into_sqlite::diagnostics::{ParseDiagnostic, ParseDiagnostics};
 use notion_into_sqlite::notion_database::parse_database_schema;
-use notion_into_sqlite::notion_pages::{parse_notion_page_list, NotionPropertyValue};
+use notion_into_sqlite::notion_pages::{
+    parse_notion_page_list, parse_notion_page_list_with_diagnostics, NotionPropertyValue,
+};
 use std::error::Error;
 
 #[test]
@@ -51,3 +54,104 @@ fn it_parses_notion_page_list_with_all_types() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn it_parses_notion_page_list_with_new_types() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_NEW_TYPES_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let pages_json =
+        serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_NEW_TYPES_JSON)?;
+    let (pages, next_cursor) = parse_notion_page_list(&schema, &pages_json)?;
+    assert!(next_cursor.is_none());
+    assert_eq!(pages.len(), 1);
+
+    let properties = &pages.first().unwrap().properties;
+    assert_eq!(properties.len(), 6);
+    assert_eq!(
+        properties.get("Status").unwrap(),
+        &NotionPropertyValue::Text("Done".to_string())
+    );
+    assert_eq!(
+        properties.get("ID").unwrap(),
+        &NotionPropertyValue::Text("TASK-42".to_string())
+    );
+    match properties.get("Place").unwrap() {
+        NotionPropertyValue::Json(place) => assert_eq!(place["name"], "Tokyo Station"),
+        value => panic!("Unexpected value: {:?}", value),
+    }
+    match properties.get("Verification").unwrap() {
+        NotionPropertyValue::Json(verification) => {
+            assert_eq!(verification["state"], "verified")
+        }
+        value => panic!("Unexpected value: {:?}", value),
+    }
+    Ok(())
+}
+
+#[test]
+fn it_reports_skipped_pages_and_properties() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let mut pages_json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_JSON)?;
+
+    // The first page has "Age" in unexpected shape, and the second page has no "url"
+    let results = pages_json["results"].as_array_mut().unwrap();
+    let mut page_without_url = results[0].clone();
+    page_without_url["id"] = "b1d2e3f4-0000-4000-8000-000000000000".into();
+    page_without_url.as_object_mut().unwrap().remove("url");
+    results[0]["properties"]["Age"]["number"] = "10".into();
+    results.push(page_without_url);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let (pages, _) =
+        parse_notion_page_list_with_diagnostics(&schema, &pages_json, &mut diagnostics)?;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].properties.len(), 2);
+
+    assert_eq!(diagnostics.skipped_pages(), 1);
+    assert_eq!(diagnostics.skipped_properties(), 1);
+    assert_eq!(
+        diagnostics.items,
+        vec![
+            ParseDiagnostic {
+                page_id: Some("a75b9220-455d-48e1-a36b-c581a345f777".to_string()),
+                property_name: Some("Age".to_string()),
+                json_path: r#"results[0].properties["Age"].number"#.to_string(),
+                reason: "value has unexpected shape".to_string(),
+            },
+            ParseDiagnostic {
+                page_id: Some("b1d2e3f4-0000-4000-8000-000000000000".to_string()),
+                property_name: None,
+                json_path: "results[1].url".to_string(),
+                reason: "field is missing".to_string(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn it_parses_empty_values_as_null() -> Result<(), Box<dyn Error>> {
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let mut pages_json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_JSON)?;
+    let properties = &mut pages_json["results"][0]["properties"];
+    properties["Age"]["number"] = serde_json::Value::Null;
+    properties["Animal"]["select"] = serde_json::Value::Null;
+    properties["Name"]["title"] = serde_json::json!([]);
+
+    let mut diagnostics = ParseDiagnostics::new();
+    let (pages, _) =
+        parse_notion_page_list_with_diagnostics(&schema, &pages_json, &mut diagnostics)?;
+    assert!(diagnostics.is_empty());
+
+    let properties = &pages.first().unwrap().properties;
+    assert_eq!(properties.len(), 3);
+    assert_eq!(properties.get("Age").unwrap(), &NotionPropertyValue::Null);
+    assert_eq!(
+        properties.get("Animal").unwrap(),
+        &NotionPropertyValue::Null
+    );
+    assert_eq!(properties.get("Name").unwrap(), &NotionPropertyValue::Null);
+    Ok(())
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "parquet")]
+
+mod common;
+
+extern crate notion_into_sqlite;
+
+use std::error::Error;
+use std::fs::File;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, TimestampMillisecondType};
+use arrow_array::RecordBatch;
+use arrow_schema::{DataType, TimeUnit};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde_json::Value;
+
+use common::{fixtures, helpers};
+use notion_into_sqlite::diagnostics::ParseDiagnostics;
+use notion_into_sqlite::exporter::{Exporter, PageSource, Sink};
+use notion_into_sqlite::notion_database::{parse_database_schema, NotionDatabaseSchema};
+use notion_into_sqlite::notion_pages::{parse_notion_page_list_with_diagnostics, NotionPage};
+use notion_into_sqlite::parquet_sink::ParquetSink;
+
+/// Page source which returns the fixture page with a different id in each of 3 batches
+struct BatchedSource;
+impl PageSource for BatchedSource {
+    fn get_database_schemas(
+        &self,
+        _database_id: &str,
+    ) -> notion_into_sqlite::error::Result<Vec<NotionDatabaseSchema>> {
+        let json = serde_json::from_str::<Value>(fixtures::NOTION_DATABASE_ALL_TYPES_JSON).unwrap();
+        Ok(vec![parse_database_schema(&json)?])
+    }
+
+    fn get_all_pages(
+        &self,
+        _database_id: &str,
+        _schema: &NotionDatabaseSchema,
+        _diagnostics: &mut ParseDiagnostics,
+    ) -> notion_into_sqlite::error::Result<Vec<NotionPage>> {
+        unreachable!("Pages are fetched batch by batch")
+    }
+
+    fn get_pages(
+        &self,
+        _database_id: &str,
+        schema: &NotionDatabaseSchema,
+        start_cursor: Option<&str>,
+        diagnostics: &mut ParseDiagnostics,
+    ) -> notion_into_sqlite::error::Result<(Vec<NotionPage>, Option<String>)> {
+        let index: usize = start_cursor.map_or(0, |cursor| cursor.parse().unwrap());
+        let mut json = serde_json::from_str::<Value>(fixtures::NOTION_LIST_ALL_TYPES_JSON).unwrap();
+        json["results"][0]["id"] = serde_json::json!(format!("page-{}", index));
+        let (pages, _) = parse_notion_page_list_with_diagnostics(schema, &json, diagnostics)?;
+        let next_cursor = Some(index + 1).filter(|next| *next < 3);
+        Ok((pages, next_cursor.map(|next| next.to_string())))
+    }
+}
+
+/// Number of row groups and record batches of the file
+fn read_parquet(path: &str) -> Result<(usize, Vec<RecordBatch>), Box<dyn Error>> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+    let row_groups = builder.metadata().num_row_groups();
+    let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+    Ok((row_groups, batches))
+}
+
+#[test]
+fn it_writes_typed_parquet_files() -> Result<(), Box<dyn Error>> {
+    let directory = "tmp/parquet1";
+    helpers::before_dir(directory);
+
+    let schema = parse_database_schema(&serde_json::from_str::<Value>(
+        fixtures::NOTION_DATABASE_ALL_TYPES_JSON,
+    )?)?;
+    let list = serde_json::from_str::<Value>(fixtures::NOTION_LIST_ALL_TYPES_JSON)?;
+    let mut diagnostics = ParseDiagnostics::new();
+    let (pages, _) = parse_notion_page_list_with_diagnostics(&schema, &list, &mut diagnostics)?;
+
+    let mut sink = ParquetSink::new(directory);
+    sink.create_table(&schema, "pages")?;
+    // Each write is a row group
+    sink.write_pages(&schema, "pages", &pages)?;
+    sink.write_pages(&schema, "pages", &pages)?;
+    sink.finish()?;
+
+    let (row_groups, batches) = read_parquet(&format!("{}/pages.parquet", directory))?;
+    assert_eq!(row_groups, 2);
+    assert_eq!(batches[0].num_rows(), 2);
+    let batch = &batches[0];
+    let column = |name: &str| batch.column_by_name(name).unwrap();
+    let timestamp = DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()));
+
+    assert_eq!(column("Number").data_type(), &DataType::Float64);
+    assert_eq!(
+        column("Number").as_primitive::<Float64Type>().value(0),
+        10.0
+    );
+    assert_eq!(column("Checkbox").data_type(), &DataType::Boolean);
+    assert_eq!(column("Date").data_type(), &timestamp);
+    assert_eq!(
+        column("Date")
+            .as_primitive::<TimestampMillisecondType>()
+            .value(0),
+        1647734400000
+    );
+    assert_eq!(column("CreatedTime").data_type(), &timestamp);
+    let multi_select = column("MultiSelect").as_list::<i32>().value(0);
+    let names = multi_select.as_string::<i32>();
+    assert_eq!(
+        names.iter().collect::<Vec<_>>(),
+        vec![Some("multi"), Some("select")]
+    );
+    assert_eq!(column("Name").data_type(), &DataType::Utf8);
+
+    let (_, metadata) = read_parquet(&format!("{}/page_metadata.parquet", directory))?;
+    assert_eq!(metadata[0].num_rows(), 1);
+    assert_eq!(
+        metadata[0]
+            .column_by_name("created_time")
+            .unwrap()
+            .data_type(),
+        &timestamp
+    );
+    Ok(())
+}
+
+#[test]
+fn it_writes_row_group_for_each_batch_of_export() -> Result<(), Box<dyn Error>> {
+    let directory = "tmp/parquet2";
+    helpers::before_dir(directory);
+
+    let summary = Exporter::builder()
+        .source(BatchedSource)
+        .database_id("8a281474-f071-4c54-8afc-17d8a4b7c782")
+        .sink(ParquetSink::new(directory))
+        .build()?
+        .run()?;
+    assert_eq!(summary.tables[0].pages, 3);
+
+    let (row_groups, batches) = read_parquet(&format!("{}/pages.parquet", directory))?;
+    assert_eq!(row_groups, 3);
+    let rows = batches.iter().map(|batch| batch.num_rows()).sum::<usize>();
+    assert_eq!(rows, 3);
+    Ok(())
+}
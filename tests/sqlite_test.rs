@@ -6,14 +6,16 @@ use rusqlite::params;
 use std::collections::HashMap;
 
 use common::{fixtures, helpers};
-use notion_into_sqlite::notion_database::parse_database_schema;
-use notion_into_sqlite::notion_pages::{NotionPage, NotionPropertyValue};
+use notion_into_sqlite::notion_database::{parse_database_schema, NotionDataSource};
+use notion_into_sqlite::notion_pages::{parse_notion_page_list, NotionPage, NotionPropertyValue};
 use notion_into_sqlite::sqlite::{
-    Sqlite, PAGE_ID_COLUMN, PAGE_METADATA_TABLE, PAGE_PROPERTIES_TABLE,
+    history_table_name, properties_table_names, Sqlite, PAGE_ID_COLUMN, PAGE_METADATA_TABLE,
+    PAGE_PROPERTIES_TABLE, STATUS_OPTIONS_TABLE,
 };
 use std::error::Error;
 
 #[test]
+#[allow(clippy::needless_question_mark)]
 fn it_creates_tables() -> Result<(), Box<dyn Error>> {
     let database_path = "tmp/test1.db";
     helpers::before_db(database_path);
@@ -137,3 +139,260 @@ fn it_inserts_notion_entry() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn it_creates_tables_for_new_property_types() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/test4.db";
+    helpers::before_db(database_path);
+
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_NEW_TYPES_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let sqlite = Sqlite::new(database_path, &schema)?;
+    sqlite.create_tables()?;
+
+    let table_def_sql: String = sqlite.conn.query_row(
+        "SELECT sql FROM sqlite_master where name=?1",
+        params![PAGE_PROPERTIES_TABLE],
+        |row| row.get(0),
+    )?;
+    assert!(table_def_sql.contains(r#""Status" TEXT"#));
+    assert!(table_def_sql.contains(r#""ID" TEXT"#));
+    assert!(!table_def_sql.contains("UNIQUE"));
+
+    let group_name: String = sqlite.conn.query_row(
+        format!(
+            "SELECT group_name FROM {table_name} WHERE property = ?1 AND option = ?2",
+            table_name = STATUS_OPTIONS_TABLE
+        )
+        .as_str(),
+        params!["Status", "In progress"],
+        |row| row.get(0),
+    )?;
+    assert_eq!(group_name, "In progress");
+
+    // Another page with the same unique ID, e.g. after re-numbering, is still inserted
+    let pages_json =
+        serde_json::from_str::<serde_json::Value>(fixtures::NOTION_LIST_NEW_TYPES_JSON)?;
+    let (mut pages, _) = parse_notion_page_list(&schema, &pages_json)?;
+    sqlite.insert(&pages[0])?;
+    pages[0].id = "another-page".to_string();
+    sqlite.insert(&pages[0])?;
+    let count: i64 = sqlite.conn.query_row(
+        r#"SELECT count(*) FROM pages WHERE "ID" IS NOT NULL"#,
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(count, 2);
+
+    Ok(())
+}
+
+#[test]
+fn it_creates_table_for_each_data_source() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/test5.db";
+    helpers::before_db(database_path);
+
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATA_SOURCE_JSON)?;
+    let tasks_schema = parse_database_schema(&json)?;
+    let mut milestones_schema = parse_database_schema(&json)?;
+    milestones_schema.data_source = Some(NotionDataSource {
+        id: "248104cd-477e-8096-9b38-000b1a2ad7a2".to_string(),
+        name: "Milestones".to_string(),
+    });
+    let schemas = vec![tasks_schema, milestones_schema];
+
+    let table_names = properties_table_names(&schemas);
+    assert_eq!(table_names, vec!["pages_tasks", "pages_milestones"]);
+    assert_eq!(properties_table_names(&schemas[..1]), vec!["pages"]);
+
+    for (schema, table_name) in schemas.iter().zip(&table_names) {
+        let sqlite = Sqlite::new_with_table_name(database_path, schema, table_name)?;
+        sqlite.create_tables()?;
+    }
+
+    let sqlite = Sqlite::new(database_path, &schemas[0])?;
+    let mut statement = sqlite
+        .conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+    let names = statement
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    assert_eq!(
+        names,
+        vec![PAGE_METADATA_TABLE, "pages_milestones", "pages_tasks"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn it_writes_null_for_empty_properties_on_upsert() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/test6.db";
+    helpers::before_db(database_path);
+
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let sqlite = Sqlite::new(database_path, &schema)?;
+    sqlite.create_tables()?;
+
+    let user = serde_json::json!({
+        "object": "user",
+        "id": "9d069f8b-6223-4853-b7eb-8fe3dfe7d389"
+    });
+    let mut page = NotionPage {
+        id: "xxxx".to_string(),
+        properties: HashMap::from([
+            (
+                "Name".to_string(),
+                NotionPropertyValue::Text("Meu".to_string()),
+            ),
+            ("Age".to_string(), NotionPropertyValue::Number(5.0)),
+            (
+                "Animal".to_string(),
+                NotionPropertyValue::Text("cat".to_string()),
+            ),
+        ]),
+        url: "https://www.notion.so/xxxx".to_string(),
+        created_time: "2022-03-12T00:15:00.000Z".to_string(),
+        created_by: user.clone(),
+        last_edited_time: "2022-03-12T00:16:00.000Z".to_string(),
+        last_edited_by: user,
+        archived: false,
+    };
+    sqlite.insert(&page)?;
+
+    // "Age" is emptied in Notion and "Animal" is missing in the response
+    page.properties
+        .insert("Age".to_string(), NotionPropertyValue::Null);
+    page.properties.remove("Animal");
+    page.last_edited_time = "2022-03-13T00:00:00.000Z".to_string();
+    sqlite.insert(&page)?;
+
+    let (count, name, age, animal): (i64, String, Option<f64>, Option<String>) =
+        sqlite.conn.query_row(
+            format!(
+                r#"SELECT count(*), "Name", "Age", "Animal" from {table_name}"#,
+                table_name = PAGE_PROPERTIES_TABLE
+            )
+            .as_str(),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+    assert_eq!(count, 1);
+    assert_eq!(name, "Meu");
+    assert_eq!(age, None);
+    assert_eq!(animal, None);
+
+    let last_edited_time: String = sqlite.conn.query_row(
+        format!(
+            "SELECT last_edited_time from {table_name}",
+            table_name = PAGE_METADATA_TABLE
+        )
+        .as_str(),
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(last_edited_time, "2022-03-13T00:00:00.000Z");
+
+    Ok(())
+}
+
+#[test]
+fn it_appends_changed_pages_to_history() -> Result<(), Box<dyn Error>> {
+    let database_path = "tmp/test7.db";
+    helpers::before_db(database_path);
+
+    let json = serde_json::from_str::<serde_json::Value>(fixtures::NOTION_DATABASE_JSON)?;
+    let schema = parse_database_schema(&json)?;
+    let sqlite = Sqlite::new(database_path, &schema)?;
+    sqlite.create_tables()?;
+    sqlite.create_history_table()?;
+
+    let user = serde_json::json!({
+        "object": "user",
+        "id": "9d069f8b-6223-4853-b7eb-8fe3dfe7d389"
+    });
+    let mut page = NotionPage {
+        id: "xxxx".to_string(),
+        properties: HashMap::from([(
+            "Animal".to_string(),
+            NotionPropertyValue::Text("kitten".to_string()),
+        )]),
+        url: "https://www.notion.so/xxxx".to_string(),
+        created_time: "2022-03-12T00:15:00.000Z".to_string(),
+        created_by: user.clone(),
+        last_edited_time: "2022-03-12T00:16:00.000Z".to_string(),
+        last_edited_by: user,
+        archived: false,
+    };
+    assert_eq!(sqlite.append_history(std::slice::from_ref(&page))?, 1);
+    // Unchanged pages aren't appended again
+    assert_eq!(sqlite.append_history(std::slice::from_ref(&page))?, 0);
+
+    page.properties.insert(
+        "Animal".to_string(),
+        NotionPropertyValue::Text("cat".to_string()),
+    );
+    page.last_edited_time = "2022-03-20T00:00:00.000Z".to_string();
+    assert_eq!(sqlite.append_history(std::slice::from_ref(&page))?, 1);
+
+    let mut statement = sqlite.conn.prepare(&format!(
+        r#"SELECT "Animal", _valid_from, _valid_to, _synced_at FROM {} ORDER BY _valid_from"#,
+        history_table_name(PAGE_PROPERTIES_TABLE)
+    ))?;
+    let versions = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(versions.len(), 2);
+    assert_eq!(
+        (versions[0].0.as_str(), versions[0].1.as_str()),
+        ("kitten", "2022-03-12T00:16:00.000Z")
+    );
+    assert_eq!(versions[0].2.as_deref(), Some("2022-03-20T00:00:00.000Z"));
+    assert_eq!(
+        (versions[1].0.as_str(), versions[1].1.as_str()),
+        ("cat", "2022-03-20T00:00:00.000Z")
+    );
+    assert_eq!(versions[1].2, None);
+    assert!(versions[1].3 >= versions[0].3);
+
+    // Version which was current at a point in time
+    let animal: String = sqlite.conn.query_row(
+        r#"SELECT "Animal" FROM pages_history WHERE page_id = 'xxxx'
+        AND _valid_from <= ?1 AND (_valid_to IS NULL OR _valid_to > ?1)"#,
+        ["2022-03-15T00:00:00.000Z"],
+        |row| row.get(0),
+    )?;
+    assert_eq!(animal, "kitten");
+
+    // Pages no longer in the database have no current version
+    sqlite.close_history(&["xxxx".to_string()])?;
+    let current: i64 = sqlite.conn.query_row(
+        "SELECT count(*) FROM pages_history WHERE _valid_to IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(current, 0);
+
+    // Columns of properties added to the database are added to the existing table
+    sqlite
+        .conn
+        .execute(r#"ALTER TABLE pages_history DROP COLUMN "Age""#, [])?;
+    sqlite.create_history_table()?;
+    assert_eq!(sqlite.append_history(std::slice::from_ref(&page))?, 1);
+    let age: Option<f64> = sqlite.conn.query_row(
+        r#"SELECT "Age" FROM pages_history WHERE _valid_to IS NULL"#,
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(age, None);
+
+    Ok(())
+}
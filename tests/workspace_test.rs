@@ -0,0 +1,189 @@
+mod common;
+
+extern crate notion_into_sqlite;
+
+use std::error::Error;
+use std::sync::Arc;
+
+use common::mock_server::MockServer;
+use notion_into_sqlite::notion_client::NotionClient;
+use notion_into_sqlite::rate_limiter::RateLimiter;
+use notion_into_sqlite::workspace::{find_databases, DiscoveredDatabase};
+use serde_json::{json, Value};
+
+static ROOT_PAGE_ID: &str = "00000000-0000-4000-8000-000000000001";
+static CHILD_PAGE_ID: &str = "00000000-0000-4000-8000-000000000002";
+static COLUMN_LIST_ID: &str = "00000000-0000-4000-8000-000000000003";
+static DATABASE_ID_1: &str = "00000000-0000-4000-8000-0000000000d1";
+static DATABASE_ID_2: &str = "00000000-0000-4000-8000-0000000000d2";
+static DATABASE_ID_3: &str = "00000000-0000-4000-8000-0000000000d3";
+static DATABASE_ID_4: &str = "00000000-0000-4000-8000-0000000000d4";
+static LINKED_DATABASE_ID: &str = "00000000-0000-4000-8000-0000000000d5";
+static ROW_PAGE_ID: &str = "00000000-0000-4000-8000-000000000004";
+
+fn block(id: &str, block_type: &str, title: &str, has_children: bool) -> Value {
+    json!({
+        "object": "block",
+        "id": id,
+        "type": block_type,
+        block_type: {"title": title},
+        "has_children": has_children,
+    })
+}
+
+fn list(results: Vec<Value>) -> (u16, String) {
+    let response = json!({
+        "object": "list",
+        "results": results,
+        "next_cursor": null,
+        "has_more": false,
+    });
+    (200, response.to_string())
+}
+
+fn database(id: &str) -> (u16, String) {
+    let database = json!({
+        "object": "database",
+        "id": id,
+        "title": [{"plain_text": "Database"}],
+        "properties": {},
+    });
+    (200, database.to_string())
+}
+
+/// Workspace like below
+/// - Root page
+///   - Tasks (database 1)
+///     - Row page
+///       - Subtasks (database 4)
+///   - Child page
+///     - Bugs (database 3)
+///     - Linked database, which can't be retrieved
+///   - Column list
+///     - Notes (inline database 2)
+fn workspace_server() -> MockServer {
+    MockServer::start(|request| {
+        let path = request.path.split('?').next().unwrap();
+        let children = |id: &str| format!("/v1/blocks/{}/children", id);
+        if path.contains(LINKED_DATABASE_ID) {
+            let error = json!({"object": "error", "status": 404, "code": "object_not_found", "message": "Could not find database"});
+            (404, error.to_string())
+        } else if path == format!("/v1/databases/{}/query", DATABASE_ID_1) {
+            list(vec![json!({"object": "page", "id": ROW_PAGE_ID})])
+        } else if path.starts_with("/v1/databases/") && !path.ends_with("/query") {
+            database(path.trim_start_matches("/v1/databases/"))
+        } else if path == children(ROW_PAGE_ID) {
+            list(vec![block(
+                DATABASE_ID_4,
+                "child_database",
+                "Subtasks",
+                false,
+            )])
+        } else if path == children(ROOT_PAGE_ID) {
+            list(vec![
+                block(DATABASE_ID_1, "child_database", "Tasks", false),
+                json!({"object": "block", "id": "00000000-0000-4000-8000-0000000000f1", "type": "paragraph", "paragraph": {}, "has_children": false}),
+                block(CHILD_PAGE_ID, "child_page", "Child page", true),
+                json!({"object": "block", "id": COLUMN_LIST_ID, "type": "column_list", "column_list": {}, "has_children": true}),
+            ])
+        } else if path == children(CHILD_PAGE_ID) {
+            list(vec![
+                block(DATABASE_ID_3, "child_database", "Bugs", false),
+                block(LINKED_DATABASE_ID, "child_database", "Linked", false),
+            ])
+        } else if path == children(COLUMN_LIST_ID) {
+            list(vec![
+                block(DATABASE_ID_2, "child_database", "Notes", false),
+                // The same database can't appear twice, but it shouldn't be exported twice anyway
+                block(DATABASE_ID_1, "child_database", "Tasks", false),
+            ])
+        } else if path == format!("/v1/blocks/{}", ROOT_PAGE_ID) {
+            (
+                200,
+                block(ROOT_PAGE_ID, "child_page", "Root page", true).to_string(),
+            )
+        } else if path == format!("/v1/blocks/{}", DATABASE_ID_1) {
+            (
+                200,
+                block(DATABASE_ID_1, "child_database", "Tasks", false).to_string(),
+            )
+        } else {
+            list(vec![])
+        }
+    })
+}
+
+fn mock_client(server: &MockServer) -> NotionClient {
+    let mut client = NotionClient::new("secret");
+    client.base_url = server.url.clone();
+    client.rate_limiter = Arc::new(RateLimiter::new(0.0));
+    client
+}
+
+#[test]
+fn it_finds_databases_under_page() -> Result<(), Box<dyn Error>> {
+    let server = workspace_server();
+    let client = mock_client(&server);
+
+    let databases = find_databases(&client, &ROOT_PAGE_ID.replace('-', ""))?;
+    assert_eq!(
+        databases,
+        vec![
+            DiscoveredDatabase {
+                id: DATABASE_ID_1.to_string(),
+                title: "Tasks".to_string(),
+                page_id: Some(ROOT_PAGE_ID.to_string()),
+            },
+            DiscoveredDatabase {
+                id: DATABASE_ID_4.to_string(),
+                title: "Subtasks".to_string(),
+                page_id: Some(ROW_PAGE_ID.to_string()),
+            },
+            DiscoveredDatabase {
+                id: DATABASE_ID_3.to_string(),
+                title: "Bugs".to_string(),
+                page_id: Some(CHILD_PAGE_ID.to_string()),
+            },
+            DiscoveredDatabase {
+                id: DATABASE_ID_2.to_string(),
+                title: "Notes".to_string(),
+                page_id: Some(ROOT_PAGE_ID.to_string()),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn it_finds_databases_in_pages_of_root_database() -> Result<(), Box<dyn Error>> {
+    let server = workspace_server();
+    let client = mock_client(&server);
+
+    let databases = find_databases(&client, DATABASE_ID_1)?;
+    let ids = databases
+        .iter()
+        .map(|database| database.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![DATABASE_ID_1, DATABASE_ID_4]);
+    assert_eq!(databases[0].page_id, None);
+    assert_eq!(databases[1].page_id.as_deref(), Some(ROW_PAGE_ID));
+    Ok(())
+}
+
+#[test]
+fn it_fails_when_no_database_is_found() {
+    let server = MockServer::start(|request| {
+        if request.path.contains("/children") {
+            list(vec![])
+        } else {
+            (
+                200,
+                block(ROOT_PAGE_ID, "child_page", "Empty", false).to_string(),
+            )
+        }
+    });
+    let client = mock_client(&server);
+
+    let err = find_databases(&client, ROOT_PAGE_ID).unwrap_err();
+    assert_eq!(err.exit_code(), 2);
+}